@@ -0,0 +1,282 @@
+use crate::commands::{cmd::Stop, playback::listener_count};
+use crate::config::ConfigStorage;
+use crate::constants::EMBED_COLOUR;
+use crate::state::{modify_guild_state, LeaveReason, PendingLeave};
+use serenity::builder::{CreateEmbed, CreateMessage};
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::model::voice::VoiceState;
+use serenity::prelude::Mutex;
+use songbird::{tracks::PlayMode, Call};
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+use tracing::{error, info};
+
+const GRACE_PERIOD: Duration = Duration::from_secs(30);
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+pub async fn handle_voice_state(ctx: &Context, old: Option<VoiceState>, new: VoiceState) {
+  let guild_id = match new.guild_id {
+    Some(g) => g,
+    None => return,
+  };
+  let old_channel = old.and_then(|o| o.channel_id);
+
+  let bot_id = ctx.cache.current_user().id;
+  if new.user_id == bot_id {
+    if old_channel == new.channel_id {
+      return;
+    }
+    // Whatever was pending was about the channel the bot just left
+    if let Some(pending) = modify_guild_state(ctx, guild_id, |s| s.pending_leave.take()).await {
+      pending.task.abort();
+    }
+    if new.channel_id.is_some() {
+      start_idle_watcher(ctx, guild_id).await;
+    }
+  }
+
+  let call = match songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+    Some(c) => c,
+    None => return,
+  };
+  let (channel, listeners) = {
+    let call = call.lock().await;
+    (call.current_channel(), listener_count(ctx, guild_id, &call))
+  };
+  let channel = match channel {
+    Some(c) => ChannelId::new(c.0.get()),
+    None => return,
+  };
+  if new.user_id != bot_id && old_channel != Some(channel) && new.channel_id != Some(channel) {
+    return;
+  }
+
+  match listeners {
+    0 => begin_leave(ctx, guild_id, LeaveReason::Alone).await,
+    _ => cancel_leave(ctx, guild_id, LeaveReason::Alone).await,
+  }
+}
+
+async fn start_idle_watcher(ctx: &Context, guild_id: GuildId) {
+  let timeout = {
+    let data = ctx.data.read().await;
+    data
+      .get::<ConfigStorage>()
+      .expect("No config in global storage")
+      .idle_timeout
+  };
+  if timeout.is_zero() {
+    return;
+  }
+
+  let watcher_ctx = ctx.clone();
+  modify_guild_state(ctx, guild_id, move |s| {
+    if s.idle_watcher.as_ref().is_some_and(|w| !w.is_finished()) {
+      return;
+    }
+    s.idle_watcher = Some(tokio::spawn(watch_idle(watcher_ctx, guild_id, timeout)));
+  })
+  .await;
+}
+
+// Runs for as long as the bot is in a call, leaving once nothing has played for the timeout
+async fn watch_idle(ctx: Context, guild_id: GuildId, timeout: Duration) {
+  let mut idle_since = None;
+  loop {
+    tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+
+    let call = match songbird::get(&ctx).await.and_then(|m| m.get(guild_id)) {
+      Some(c) => c,
+      None => return,
+    };
+    if call.lock().await.current_channel().is_none() {
+      return;
+    }
+
+    if is_playing(&call).await {
+      idle_since = None;
+      continue;
+    }
+    if idle_since.get_or_insert_with(Instant::now).elapsed() >= timeout {
+      begin_leave(&ctx, guild_id, LeaveReason::Idle).await;
+      idle_since = None;
+    }
+  }
+}
+
+async fn is_playing(call: &Arc<Mutex<Call>>) -> bool {
+  let current = call.lock().await.queue().current();
+  match current {
+    Some(track) => track
+      .get_info()
+      .await
+      .is_ok_and(|s| matches!(s.playing, PlayMode::Play)),
+    None => false,
+  }
+}
+
+async fn begin_leave(ctx: &Context, guild_id: GuildId, reason: LeaveReason) {
+  let call = match songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+    Some(c) => c,
+    None => return,
+  };
+
+  let leave_ctx = ctx.clone();
+  let started = modify_guild_state(ctx, guild_id, move |s| {
+    if s.pending_leave.is_some() {
+      return false;
+    }
+    s.pending_leave = Some(PendingLeave {
+      reason,
+      paused: false,
+      task: tokio::spawn(finish_leave(leave_ctx, guild_id, reason)),
+    });
+    true
+  })
+  .await;
+  if !started {
+    return;
+  }
+  info!(
+    "Leaving Guild({}) in {}s: {:?}",
+    guild_id,
+    GRACE_PERIOD.as_secs(),
+    reason
+  );
+
+  // Nobody is listening, so hold the track where it is in case they come back
+  if reason == LeaveReason::Alone && is_playing(&call).await {
+    if let Err(e) = call.lock().await.queue().pause() {
+      error!("Error pausing before leaving: {}", e);
+      return;
+    }
+    modify_guild_state(ctx, guild_id, |s| {
+      if let Some(pending) = s.pending_leave.as_mut() {
+        pending.paused = true;
+      }
+    })
+    .await;
+  }
+}
+
+async fn cancel_leave(ctx: &Context, guild_id: GuildId, reason: LeaveReason) {
+  let pending = modify_guild_state(ctx, guild_id, |s| {
+    match s.pending_leave.as_ref().is_some_and(|p| p.reason == reason) {
+      true => s.pending_leave.take(),
+      false => None,
+    }
+  })
+  .await;
+  let pending = match pending {
+    Some(p) => p,
+    None => return,
+  };
+  pending.task.abort();
+  info!("Staying in Guild({})", guild_id);
+
+  if pending.paused {
+    if let Some(call) = songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+      if let Err(e) = call.lock().await.queue().resume() {
+        error!("Error resuming after staying: {}", e);
+      }
+    }
+  }
+}
+
+async fn finish_leave(ctx: Context, guild_id: GuildId, reason: LeaveReason) {
+  tokio::time::sleep(GRACE_PERIOD).await;
+
+  let manager = match songbird::get(&ctx).await {
+    Some(arc) => arc.clone(),
+    None => {
+      error!("Error with songbird client");
+      return;
+    }
+  };
+  let call = match manager.get(guild_id) {
+    Some(c) => c,
+    None => {
+      modify_guild_state(&ctx, guild_id, |s| s.pending_leave = None).await;
+      return;
+    }
+  };
+
+  // Something may have started playing since, which only resuming or queueing would show
+  let still_applies = match reason {
+    LeaveReason::Alone => listener_count(&ctx, guild_id, &*call.lock().await) == 0,
+    LeaveReason::Idle => !is_playing(&call).await,
+  };
+  let (paused, text_channel, now_playing_channel) = modify_guild_state(&ctx, guild_id, |s| {
+    (
+      s.pending_leave.take().is_some_and(|p| p.paused),
+      s.text_channel,
+      s.now_playing.as_ref().map(|p| p.channel_id),
+    )
+  })
+  .await;
+  if !still_applies {
+    info!("Staying in Guild({})", guild_id);
+    if paused {
+      if let Err(e) = call.lock().await.queue().resume() {
+        error!("Error resuming after staying: {}", e);
+      }
+    }
+    return;
+  }
+
+  let voice_channel = call
+    .lock()
+    .await
+    .current_channel()
+    .map(|c| ChannelId::new(c.0.get()));
+  if let Err(e) = manager.remove(guild_id).await {
+    error!("Error leaving voice channel: {}", e);
+    return;
+  }
+  {
+    let handler = call.lock().await;
+    Stop::stop_playback(&ctx, guild_id, &handler).await;
+  }
+  info!("Left Guild({}): {:?}", guild_id, reason);
+
+  let description = match reason {
+    LeaveReason::Alone => "Everyone else left the channel".to_string(),
+    LeaveReason::Idle => {
+      let data = ctx.data.read().await;
+      let timeout = data
+        .get::<ConfigStorage>()
+        .expect("No config in global storage")
+        .idle_timeout;
+      format!("Nothing played for {}", format_timeout(timeout))
+    }
+  };
+  let channel = match text_channel.or(now_playing_channel).or(voice_channel) {
+    Some(c) => c,
+    None => return,
+  };
+  if let Err(e) = channel
+    .send_message(
+      &ctx.http,
+      CreateMessage::new().embed(
+        CreateEmbed::new()
+          .title("Left the voice channel")
+          .description(description)
+          .colour(EMBED_COLOUR),
+      ),
+    )
+    .await
+  {
+    error!("Error sending leave notice: {}", e);
+  }
+}
+
+fn format_timeout(timeout: Duration) -> String {
+  match timeout.as_secs() {
+    60 => "a minute".to_string(),
+    s if s % 60 == 0 => format!("{} minutes", s / 60),
+    s => format!("{} seconds", s),
+  }
+}
@@ -0,0 +1,232 @@
+use crate::commands::{playback::format_duration_live, utils::truncate_unicode};
+use crate::constants::HttpKey;
+use crate::storage::database;
+use serenity::{
+  builder::{CreateAutocompleteResponse, CreateInteractionResponse},
+  client::Context,
+  model::application::CommandInteraction,
+  model::id::{GuildId, UserId},
+  prelude::{Mutex, TypeMapKey},
+  Error,
+};
+use songbird::input::YoutubeDl;
+use std::{
+  collections::HashMap,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+use tracing::error;
+
+const DEBOUNCE: Duration = Duration::from_millis(350);
+const SEARCH_DEADLINE: Duration = Duration::from_secs(2);
+const CACHE_TTL: Duration = Duration::from_secs(600);
+const CACHE_LIMIT: usize = 200;
+
+const MIN_SEARCH_LENGTH: usize = 3;
+const SEARCH_RESULTS: usize = 5;
+const HISTORY_LOOKUP: usize = 100;
+const MAX_SUGGESTIONS: usize = 25;
+const MAX_CHOICE_LENGTH: usize = 100;
+
+pub struct AutocompleteStorage;
+
+impl TypeMapKey for AutocompleteStorage {
+  type Value = Arc<Mutex<AutocompleteCache>>;
+}
+
+#[derive(Default)]
+pub struct AutocompleteCache {
+  searches: HashMap<String, CachedSearch>,
+  latest: HashMap<UserId, u64>,
+  counter: u64,
+}
+
+struct CachedSearch {
+  fetched: Instant,
+  suggestions: Vec<Suggestion>,
+}
+
+#[derive(Clone)]
+struct Suggestion {
+  name: String,
+  value: String,
+}
+
+impl Suggestion {
+  fn new(name: &str, value: String) -> Option<Self> {
+    match value.chars().count() <= MAX_CHOICE_LENGTH {
+      true => Some(Self {
+        name: truncate_unicode(name, MAX_CHOICE_LENGTH - 3),
+        value,
+      }),
+      false => None,
+    }
+  }
+}
+
+async fn storage(ctx: &Context) -> Arc<Mutex<AutocompleteCache>> {
+  let data = ctx.data.read().await;
+  data
+    .get::<AutocompleteStorage>()
+    .expect("No autocomplete cache in global storage")
+    .clone()
+}
+
+pub async fn play_suggestions(
+  ctx: &Context,
+  interaction: &CommandInteraction,
+) -> Result<(), Error> {
+  let query = match interaction.data.autocomplete() {
+    Some(o) => o.value.trim().to_string(),
+    None => return Ok(()),
+  };
+
+  let cache = storage(ctx).await;
+  let user_id = interaction.user.id;
+  let ticket = {
+    let mut cache = cache.lock().await;
+    cache.counter += 1;
+    let ticket = cache.counter;
+    cache.latest.insert(user_id, ticket);
+    ticket
+  };
+
+  // Every keystroke sends a request, only answer the last one once typing pauses
+  tokio::time::sleep(DEBOUNCE).await;
+  if cache.lock().await.latest.get(&user_id) != Some(&ticket) {
+    return Ok(());
+  }
+
+  let mut suggestions = match interaction.guild_id {
+    Some(guild_id) => recent_suggestions(ctx, guild_id, &query).await,
+    None => vec![],
+  };
+  if query.chars().count() >= MIN_SEARCH_LENGTH && !query.starts_with("http") {
+    suggestions.extend(search_suggestions(ctx, cache.clone(), &query).await);
+  }
+
+  {
+    let mut cache = cache.lock().await;
+    if cache.latest.get(&user_id) == Some(&ticket) {
+      cache.latest.remove(&user_id);
+    }
+  }
+
+  let mut seen = Vec::new();
+  let response = suggestions
+    .into_iter()
+    .filter(|s| {
+      let new = !seen.contains(&s.value);
+      seen.push(s.value.clone());
+      new
+    })
+    .take(MAX_SUGGESTIONS)
+    .fold(CreateAutocompleteResponse::new(), |response, s| {
+      response.add_string_choice(s.name, s.value)
+    });
+
+  interaction
+    .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+    .await
+}
+
+async fn recent_suggestions(ctx: &Context, guild_id: GuildId, query: &str) -> Vec<Suggestion> {
+  let history = match database(ctx)
+    .await
+    .recent_history(guild_id, HISTORY_LOOKUP)
+    .await
+  {
+    Ok(h) => h,
+    Err(e) => {
+      error!("Error reading play history: {}", e);
+      return vec![];
+    }
+  };
+
+  let query = query.to_lowercase();
+  history
+    .into_iter()
+    .filter(|t| t.title.to_lowercase().contains(&query))
+    .filter_map(|t| {
+      let duration = format_duration_live(t.duration, &t.title);
+      Suggestion::new(&format!("{} ({})", t.title, duration), t.url)
+    })
+    .collect()
+}
+
+async fn search_suggestions(
+  ctx: &Context,
+  cache: Arc<Mutex<AutocompleteCache>>,
+  query: &str,
+) -> Vec<Suggestion> {
+  let key = query.to_lowercase();
+  if let Some(cached) = cache.lock().await.searches.get(&key) {
+    if cached.fetched.elapsed() < CACHE_TTL {
+      return cached.suggestions.clone();
+    }
+  }
+
+  let http_client = {
+    let data = ctx.data.read().await;
+    data
+      .get::<HttpKey>()
+      .cloned()
+      .expect("HttpClient did not exist")
+  };
+
+  // The search keeps running past the deadline so the next keystroke can use its results
+  let query = query.to_string();
+  let search = tokio::spawn(async move {
+    let results = match YoutubeDl::new_search(http_client, query)
+      .search(Some(SEARCH_RESULTS))
+      .await
+    {
+      Ok(r) => Vec::from_iter(r),
+      Err(e) => {
+        error!("Error searching for autocomplete: {}", e);
+        return vec![];
+      }
+    };
+
+    let suggestions = results
+      .iter()
+      .filter_map(|r| {
+        let title = r.title.clone().unwrap_or_else(|| "N/A".to_string());
+        let duration = format_duration_live(r.duration.unwrap_or_default(), &title);
+        Suggestion::new(&format!("{} ({})", title, duration), r.source_url.clone()?)
+      })
+      .collect::<Vec<_>>();
+
+    let mut cache = cache.lock().await;
+    cache
+      .searches
+      .retain(|_, c| c.fetched.elapsed() < CACHE_TTL);
+    if cache.searches.len() >= CACHE_LIMIT {
+      let oldest = cache
+        .searches
+        .iter()
+        .min_by_key(|(_, c)| c.fetched)
+        .map(|(k, _)| k.clone());
+      if let Some(oldest) = oldest {
+        cache.searches.remove(&oldest);
+      }
+    }
+    cache.searches.insert(
+      key,
+      CachedSearch {
+        fetched: Instant::now(),
+        suggestions: suggestions.clone(),
+      },
+    );
+    suggestions
+  });
+
+  match tokio::time::timeout(SEARCH_DEADLINE, search).await {
+    Ok(Ok(suggestions)) => suggestions,
+    Ok(Err(e)) => {
+      error!("Autocomplete search failed: {}", e);
+      vec![]
+    }
+    Err(_) => vec![],
+  }
+}
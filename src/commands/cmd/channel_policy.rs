@@ -0,0 +1,68 @@
+use crate::commands::{
+  playback::{guild_settings, save_guild_settings},
+  text_response, Command,
+};
+use crate::storage::ChannelPolicy as Policy;
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::model::Permissions;
+use serenity::Error;
+use tracing::error;
+
+pub struct ChannelPolicy;
+
+const POLICY_OPTION_NAME: &str = "policy";
+
+fn describe(policy: Policy) -> &'static str {
+  match policy {
+    Policy::Locked => "Staying in the voice channel while anyone is listening there",
+    Policy::Follow => "Moving the queue to whoever uses me from another voice channel",
+  }
+}
+
+#[async_trait]
+impl Command for ChannelPolicy {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let mut settings = guild_settings(ctx, guild_id).await;
+    let policy = match command.data.options().iter().find_map(|o| match o.value {
+      ResolvedValue::String(s) if o.name == POLICY_OPTION_NAME => Some(Policy::from_name(s)),
+      _ => None,
+    }) {
+      Some(Some(p)) => p,
+      Some(None) => return text_response(ctx, command, "Invalid channel policy").await,
+      None => return text_response(ctx, command, describe(settings.channel_policy)).await,
+    };
+
+    settings.channel_policy = policy;
+    if let Err(e) = save_guild_settings(ctx, guild_id, settings).await {
+      error!("Error saving guild settings: {}", e);
+      return text_response(ctx, command, "Couldn't change channel policy").await;
+    }
+    text_response(ctx, command, describe(policy)).await
+  }
+
+  fn name() -> &'static str {
+    "channelpolicy"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Show or change whether the bot follows users to other voice channels")
+      .default_member_permissions(Permissions::MANAGE_GUILD)
+      .add_option(Policy::ALL.iter().fold(
+        CreateCommandOption::new(
+          CommandOptionType::String,
+          POLICY_OPTION_NAME,
+          "Locked stays while others listen, follow moves the whole queue",
+        ),
+        |option, policy| option.add_string_choice(policy.name(), policy.name()),
+      ))
+  }
+}
@@ -0,0 +1,76 @@
+use crate::commands::{
+  crossfade::MAX_CROSSFADE_SECS,
+  playback::{guild_settings, save_guild_settings},
+  text_response, Command,
+};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::model::Permissions;
+use serenity::Error;
+use tracing::error;
+
+pub struct Crossfade;
+
+const SECONDS_OPTION_NAME: &str = "seconds";
+
+#[async_trait]
+impl Command for Crossfade {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let seconds = match command.data.options().iter().find_map(|o| match o.value {
+      ResolvedValue::Integer(i) if o.name == SECONDS_OPTION_NAME => u16::try_from(i).ok(),
+      _ => None,
+    }) {
+      Some(s) => s.min(MAX_CROSSFADE_SECS),
+      None => {
+        let current = guild_settings(ctx, guild_id).await.crossfade_secs;
+        let text = match current {
+          0 => "Crossfade is off".to_string(),
+          s => format!("Crossfading {}s between tracks", s),
+        };
+        return text_response(ctx, command, text).await;
+      }
+    };
+
+    let mut settings = guild_settings(ctx, guild_id).await;
+    settings.crossfade_secs = seconds;
+    if let Err(e) = save_guild_settings(ctx, guild_id, settings).await {
+      error!("Error saving guild settings: {}", e);
+      return text_response(ctx, command, "Couldn't change crossfade").await;
+    }
+
+    let text = match seconds {
+      0 => "Crossfade disabled".to_string(),
+      s => format!(
+        "Crossfading {}s between tracks, except out of live streams",
+        s
+      ),
+    };
+    text_response(ctx, command, text).await
+  }
+
+  fn name() -> &'static str {
+    "crossfade"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Show or change how long tracks fade into each other")
+      .default_member_permissions(Permissions::MANAGE_GUILD)
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::Integer,
+          SECONDS_OPTION_NAME,
+          "Crossfade length in seconds, 0 to disable",
+        )
+        .min_int_value(0)
+        .max_int_value(MAX_CROSSFADE_SECS.into()),
+      )
+  }
+}
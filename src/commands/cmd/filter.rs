@@ -0,0 +1,172 @@
+use crate::commands::{playback::VOIPData, text_response, Command};
+use crate::filters::{band_name, FilterPreset, FilterSettings, EQ_BANDS, MAX_EQ_GAIN};
+use crate::state::modify_guild_state;
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{
+  CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+};
+use serenity::Error;
+use tracing::error;
+
+pub struct Filter;
+
+const PRESET_SUBCOMMAND: &str = "preset";
+const EQUALIZER_SUBCOMMAND: &str = "equalizer";
+const RESET_SUBCOMMAND: &str = "reset";
+
+const NAME_OPTION_NAME: &str = "name";
+const BAND_OPTION_NAME: &str = "band";
+const GAIN_OPTION_NAME: &str = "gain";
+
+#[async_trait]
+impl Command for Filter {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let (subcommand, options) = match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+      }) => (name, options),
+      _ => {
+        error!("No subcommand provided");
+        return text_response(ctx, command, "Invalid subcommand").await;
+      }
+    };
+
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    if let Some(h) = songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+      let voip_data = match VOIPData::from(ctx, command).await {
+        Ok(v) => v,
+        Err(s) => return text_response(ctx, command, s).await,
+      };
+      if !voip_data.compare_to_call(&h).await {
+        return text_response(ctx, command, "You're not in the voice channel").await;
+      }
+    }
+
+    let change: Box<dyn FnOnce(&mut FilterSettings) + Send> = match subcommand {
+      PRESET_SUBCOMMAND => {
+        let preset = match options.iter().find_map(|o| match o.value {
+          ResolvedValue::String(s) if o.name == NAME_OPTION_NAME => FilterPreset::from_name(s),
+          _ => None,
+        }) {
+          Some(p) => p,
+          None => return text_response(ctx, command, "Invalid filter preset").await,
+        };
+        Box::new(move |s| s.preset = preset)
+      }
+      EQUALIZER_SUBCOMMAND => {
+        let band = match options.iter().find_map(|o| match o.value {
+          ResolvedValue::Integer(i) if o.name == BAND_OPTION_NAME => {
+            EQ_BANDS.iter().position(|f| i64::from(*f) == i)
+          }
+          _ => None,
+        }) {
+          Some(b) => b,
+          None => return text_response(ctx, command, "Invalid equalizer band").await,
+        };
+        let gain = options
+          .iter()
+          .find_map(|o| match o.value {
+            ResolvedValue::Number(n) if o.name == GAIN_OPTION_NAME => Some(n as f32),
+            _ => None,
+          })
+          .unwrap_or(0.0)
+          .clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN);
+        Box::new(move |s| s.equalizer[band] = gain)
+      }
+      RESET_SUBCOMMAND => Box::new(|s| {
+        *s = FilterSettings {
+          normalize: s.normalize,
+          ..Default::default()
+        }
+      }),
+      _ => return text_response(ctx, command, "Invalid subcommand").await,
+    };
+
+    let filters = modify_guild_state(ctx, guild_id, |s| s.filters.clone()).await;
+    let settings = {
+      let mut settings = filters.write().unwrap_or_else(|e| e.into_inner());
+      change(&mut settings);
+      settings.clone()
+    };
+
+    text_response(ctx, command, describe(&settings)).await
+  }
+
+  fn name() -> &'static str {
+    "filter"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Apply audio filters to playback")
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          PRESET_SUBCOMMAND,
+          "Apply a filter preset",
+        )
+        .add_sub_option(
+          FilterPreset::ALL.iter().fold(
+            CreateCommandOption::new(CommandOptionType::String, NAME_OPTION_NAME, "Preset")
+              .required(true),
+            |option, preset| option.add_string_choice(preset.name(), preset.name()),
+          ),
+        ),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          EQUALIZER_SUBCOMMAND,
+          "Boost or cut a frequency band",
+        )
+        .add_sub_option(
+          EQ_BANDS.iter().fold(
+            CreateCommandOption::new(CommandOptionType::Integer, BAND_OPTION_NAME, "Band")
+              .required(true),
+            |option, frequency| option.add_int_choice(band_name(*frequency), *frequency as i32),
+          ),
+        )
+        .add_sub_option(
+          CreateCommandOption::new(CommandOptionType::Number, GAIN_OPTION_NAME, "Gain in dB")
+            .min_number_value(-f64::from(MAX_EQ_GAIN))
+            .max_number_value(f64::from(MAX_EQ_GAIN))
+            .required(true),
+        ),
+      )
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        RESET_SUBCOMMAND,
+        "Remove all filters",
+      ))
+  }
+}
+
+fn describe(settings: &FilterSettings) -> String {
+  if !settings.is_active() {
+    return "Filters disabled".to_string();
+  }
+
+  let bands = EQ_BANDS
+    .iter()
+    .zip(settings.equalizer)
+    .filter(|(_, gain)| *gain != 0.0)
+    .map(|(frequency, gain)| format!("{} {:+} dB", band_name(*frequency), gain))
+    .collect::<Vec<_>>();
+
+  match bands.is_empty() {
+    true => format!("Filter: {}", settings.preset),
+    false => format!(
+      "Filter: {}, equalizer: {}",
+      settings.preset,
+      bands.join(", ")
+    ),
+  }
+}
@@ -0,0 +1,86 @@
+use crate::commands::{
+  text_response,
+  utils::{remove_md_characters, truncate_unicode},
+  Command,
+};
+use crate::constants::EMBED_COLOUR;
+use crate::storage::database;
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateEmbed, EditInteractionResponse};
+use serenity::client::Context;
+use serenity::model::application::CommandInteraction;
+use serenity::Error;
+use tracing::error;
+
+pub struct History;
+
+const HISTORY_SHOWN: usize = 15;
+
+#[async_trait]
+impl Command for History {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let history = match database(ctx)
+      .await
+      .recent_history(guild_id, HISTORY_SHOWN)
+      .await
+    {
+      Ok(h) => h,
+      Err(e) => {
+        error!("Error reading play history: {}", e);
+        return text_response(ctx, command, "Couldn't read history").await;
+      }
+    };
+
+    let lines = history
+      .iter()
+      .enumerate()
+      .map(|(i, t)| {
+        let requested_by = match &t.requested_by {
+          Some(name) => format!(", requested by {}", remove_md_characters(name.clone())),
+          None => "".to_string(),
+        };
+        format!(
+          "**{}.** [{}]({}) - <t:{}:R>{}",
+          i + 1,
+          remove_md_characters(truncate_unicode(&t.title, 67)),
+          t.url,
+          t.played_at,
+          requested_by
+        )
+      })
+      .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+      return text_response(ctx, command, "Nothing has been played yet").await;
+    }
+
+    match command
+      .edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embed(
+          CreateEmbed::new()
+            .title("Recently played")
+            .colour(EMBED_COLOUR)
+            .description(lines.join("\n")),
+        ),
+      )
+      .await
+    {
+      Ok(_m) => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  fn name() -> &'static str {
+    "history"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name()).description("Show recently played tracks")
+  }
+}
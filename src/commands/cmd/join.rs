@@ -1,4 +1,4 @@
-use crate::commands::{playback::VOIPData, text_response, Command};
+use crate::commands::{connection, playback::VOIPData, text_response, Command};
 use serenity::async_trait;
 use serenity::builder::CreateCommand;
 use serenity::client::Context;
@@ -17,7 +17,6 @@ impl Command for Join {
       Err(s) => return text_response(ctx, command, s).await,
     };
 
-    let guild_id = voip_data.guild_id;
     let channel_id = voip_data.channel_id;
 
     let manager = match manager_f.await {
@@ -28,7 +27,17 @@ impl Command for Join {
       }
     };
 
-    let _handler = manager.join(guild_id, channel_id).await;
+    // An existing call only moves when the channel policy allows it
+    match manager.get(voip_data.guild_id) {
+      Some(h) => {
+        if let Err(s) = voip_data.reconcile_call(ctx, &h).await {
+          return text_response(ctx, command, s).await;
+        }
+      }
+      None => {
+        let _handler = connection::join(ctx, &manager, &voip_data).await;
+      }
+    }
 
     match channel_id.name(&ctx.http).await {
       Ok(channel_name) => {
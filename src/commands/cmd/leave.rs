@@ -1,5 +1,5 @@
 use crate::commands::text_response;
-use crate::commands::{playback::VOIPData, Command};
+use crate::commands::{cmd::Stop, playback::VOIPData, Command};
 use serenity::async_trait;
 use serenity::builder::CreateCommand;
 use serenity::client::Context;
@@ -37,7 +37,7 @@ impl Command for Leave {
         return text_response(ctx, command, "Error leaving channel").await;
       } else {
         let handler = handler_lock.lock().await;
-        handler.queue().stop();
+        Stop::stop_playback(ctx, guild_id, &handler).await;
         return text_response(ctx, command, "Left channel").await;
       }
     } else {
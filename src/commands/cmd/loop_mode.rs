@@ -0,0 +1,109 @@
+use crate::commands::{playback::VOIPData, text_response, Command};
+use crate::state::{modify_guild_state, LoopMode};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::model::id::GuildId;
+use serenity::Error;
+use songbird::Call;
+use tracing::error;
+
+pub struct Loop;
+
+impl Loop {
+  pub async fn set_mode(ctx: &Context, guild_id: GuildId, call: &Call, mode: LoopMode) {
+    modify_guild_state(ctx, guild_id, |s| s.loop_mode = mode).await;
+
+    for handle in call.queue().current_queue() {
+      let result = match mode {
+        LoopMode::Track => handle.enable_loop(),
+        _ => handle.disable_loop(),
+      };
+      if let Err(e) = result {
+        error!("Error changing track loop state: {}", e);
+      }
+    }
+  }
+}
+
+const MODE_OPTION_NAME: &str = "mode";
+
+#[async_trait]
+impl Command for Loop {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let voip_data = match VOIPData::from(ctx, command).await {
+      Ok(v) => v,
+      Err(s) => return text_response(ctx, command, s).await,
+    };
+
+    let mode = match command
+      .data
+      .options()
+      .iter()
+      .find(|o| o.name == MODE_OPTION_NAME)
+    {
+      Some(o) => match o.value {
+        ResolvedValue::String(s) => match LoopMode::from_name(s) {
+          Some(m) => m,
+          None => return text_response(ctx, command, "Invalid loop mode").await,
+        },
+        _ => {
+          error!("Invalid option type");
+          return text_response(ctx, command, "Invalid loop mode").await;
+        }
+      },
+      None => {
+        error!("No options provided");
+        return text_response(ctx, command, "No loop mode in request").await;
+      }
+    };
+
+    let guild_id = voip_data.guild_id;
+
+    let manager = match songbird::get(ctx).await {
+      Some(arc) => arc.clone(),
+      None => {
+        error!("Error with songbird client");
+        return text_response(ctx, command, "Error getting voice client").await;
+      }
+    };
+
+    let handler_lock = match manager.get(guild_id) {
+      Some(h) => {
+        if voip_data.compare_to_call(&h).await {
+          h
+        } else {
+          return text_response(ctx, command, "You're not in the voice channel").await;
+        }
+      }
+      None => return text_response(ctx, command, "Not in a voice channel").await,
+    };
+
+    let handler = handler_lock.lock().await;
+    Self::set_mode(ctx, guild_id, &handler, mode).await;
+
+    let text = match mode {
+      LoopMode::Track => "Looping the current track",
+      LoopMode::Queue => "Looping the queue",
+      LoopMode::Off => "Looping disabled",
+    };
+    text_response(ctx, command, text).await
+  }
+
+  fn name() -> &'static str {
+    "loop"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Repeat the current track or the whole queue")
+      .add_option(
+        LoopMode::ALL.iter().fold(
+          CreateCommandOption::new(CommandOptionType::String, MODE_OPTION_NAME, "Loop mode")
+            .required(true),
+          |option, mode| option.add_string_choice(mode.name(), mode.name()),
+        ),
+      )
+  }
+}
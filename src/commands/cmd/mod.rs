@@ -8,7 +8,7 @@ mod play;
 pub use play::Play;
 
 mod skip;
-pub use skip::Skip;
+pub use skip::{Skip, SkipOutcome};
 
 mod queue;
 pub use queue::Queue;
@@ -39,3 +39,42 @@ pub use pause::Pause;
 
 mod resume;
 pub use resume::Resume;
+
+mod loop_mode;
+pub use loop_mode::Loop;
+
+mod search;
+pub use search::Search;
+
+mod playlist;
+pub use playlist::Playlist;
+
+mod history;
+pub use history::History;
+
+mod previous;
+pub use previous::Previous;
+
+mod volume;
+pub use volume::Volume;
+
+mod filter;
+pub use filter::Filter;
+
+mod normalize;
+pub use normalize::Normalize;
+
+mod crossfade;
+pub use crossfade::Crossfade;
+
+mod voteskip;
+pub use voteskip::VoteSkip;
+
+mod permissions;
+pub use permissions::Permissions;
+
+mod channel_policy;
+pub use channel_policy::ChannelPolicy;
+
+mod play_this;
+pub use play_this::PlayThis;
@@ -0,0 +1,67 @@
+use crate::commands::{
+  playback::{guild_settings, save_guild_settings},
+  text_response, Command,
+};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::model::Permissions;
+use serenity::Error;
+use tracing::error;
+
+pub struct Normalize;
+
+const ENABLED_OPTION_NAME: &str = "enabled";
+
+#[async_trait]
+impl Command for Normalize {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let enabled = match command.data.options().iter().find_map(|o| match o.value {
+      ResolvedValue::Boolean(b) if o.name == ENABLED_OPTION_NAME => Some(b),
+      _ => None,
+    }) {
+      Some(b) => b,
+      None => {
+        error!("No options provided");
+        return text_response(ctx, command, "No setting in request").await;
+      }
+    };
+
+    let mut settings = guild_settings(ctx, guild_id).await;
+    settings.normalize = enabled;
+    if let Err(e) = save_guild_settings(ctx, guild_id, settings).await {
+      error!("Error saving guild settings: {}", e);
+      return text_response(ctx, command, "Couldn't change normalisation").await;
+    }
+
+    let text = match enabled {
+      true => "Loudness normalisation enabled",
+      false => "Loudness normalisation disabled",
+    };
+    text_response(ctx, command, text).await
+  }
+
+  fn name() -> &'static str {
+    "normalize"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Play every track at a consistent loudness")
+      .default_member_permissions(Permissions::MANAGE_GUILD)
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::Boolean,
+          ENABLED_OPTION_NAME,
+          "Whether loudness normalisation is on",
+        )
+        .required(true),
+      )
+  }
+}
@@ -0,0 +1,229 @@
+use crate::commands::{permissions::RESTRICTABLE, text_response, Command};
+use crate::constants::EMBED_COLOUR;
+use crate::storage::{database, CommandPermission, PermissionTarget};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed, EditInteractionResponse};
+use serenity::client::Context;
+use serenity::model::application::{
+  CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+};
+use serenity::model::Permissions as DiscordPermissions;
+use serenity::Error;
+use tracing::error;
+
+pub struct Permissions;
+
+const ALLOW_SUBCOMMAND: &str = "allow";
+const REVOKE_SUBCOMMAND: &str = "revoke";
+const RESET_SUBCOMMAND: &str = "reset";
+const LIST_SUBCOMMAND: &str = "list";
+
+const COMMAND_OPTION_NAME: &str = "command";
+const ROLE_OPTION_NAME: &str = "role";
+const USER_OPTION_NAME: &str = "user";
+
+#[async_trait]
+impl Command for Permissions {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let (subcommand, options) = match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+      }) => (name, options),
+      _ => {
+        error!("No subcommand provided");
+        return text_response(ctx, command, "Invalid subcommand").await;
+      }
+    };
+
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+    let database = database(ctx).await;
+
+    if subcommand == LIST_SUBCOMMAND {
+      return match database.command_permissions(guild_id).await {
+        Ok(p) => list(ctx, command, p).await,
+        Err(e) => {
+          error!("Error reading command permissions: {}", e);
+          text_response(ctx, command, "Couldn't read permissions").await
+        }
+      };
+    }
+
+    let name = match options.iter().find_map(|o| match o.value {
+      ResolvedValue::String(s) if o.name == COMMAND_OPTION_NAME => {
+        RESTRICTABLE.iter().find(|r| **r == s)
+      }
+      _ => None,
+    }) {
+      Some(n) => n.to_string(),
+      None => return text_response(ctx, command, "Invalid command").await,
+    };
+
+    if subcommand == RESET_SUBCOMMAND {
+      return match database
+        .clear_command_permissions(guild_id, name.clone())
+        .await
+      {
+        Ok(_) => text_response(ctx, command, format!("Anyone can use {} again", name)).await,
+        Err(e) => {
+          error!("Error clearing command permissions: {}", e);
+          text_response(ctx, command, "Couldn't change permissions").await
+        }
+      };
+    }
+
+    let target = match options.iter().find_map(|o| match o.value {
+      ResolvedValue::Role(role) if o.name == ROLE_OPTION_NAME => {
+        Some(PermissionTarget::Role(role.id))
+      }
+      ResolvedValue::User(user, _) if o.name == USER_OPTION_NAME => {
+        Some(PermissionTarget::User(user.id))
+      }
+      _ => None,
+    }) {
+      Some(t) => t,
+      None => return text_response(ctx, command, "Pick a role or a user").await,
+    };
+
+    let result = match subcommand {
+      ALLOW_SUBCOMMAND => database
+        .add_command_permission(guild_id, name.clone(), target)
+        .await
+        .map(|_| format!("{} can now use {}", target.mention(), name)),
+      REVOKE_SUBCOMMAND => database
+        .remove_command_permission(guild_id, name.clone(), target)
+        .await
+        .map(|removed| match removed {
+          true => format!("{} can no longer use {}", target.mention(), name),
+          false => format!("{} had no rule for {}", target.mention(), name),
+        }),
+      _ => return text_response(ctx, command, "Invalid subcommand").await,
+    };
+
+    match result {
+      Ok(text) => description_response(ctx, command, "Permissions updated", text).await,
+      Err(e) => {
+        error!("Error changing command permissions: {}", e);
+        text_response(ctx, command, "Couldn't change permissions").await
+      }
+    }
+  }
+
+  fn name() -> &'static str {
+    "permissions"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Limit who can use destructive commands")
+      .default_member_permissions(DiscordPermissions::MANAGE_GUILD)
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          ALLOW_SUBCOMMAND,
+          "Let a role or user use a command, limiting it to those allowed",
+        )
+        .add_sub_option(command_option())
+        .add_sub_option(role_option())
+        .add_sub_option(user_option()),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          REVOKE_SUBCOMMAND,
+          "Remove a role or user from a command's rules",
+        )
+        .add_sub_option(command_option())
+        .add_sub_option(role_option())
+        .add_sub_option(user_option()),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          RESET_SUBCOMMAND,
+          "Open a command to everyone again",
+        )
+        .add_sub_option(command_option()),
+      )
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        LIST_SUBCOMMAND,
+        "Show the permission rules",
+      ))
+  }
+}
+
+fn command_option() -> CreateCommandOption {
+  RESTRICTABLE.iter().fold(
+    CreateCommandOption::new(CommandOptionType::String, COMMAND_OPTION_NAME, "Command")
+      .required(true),
+    |option, name| option.add_string_choice(*name, *name),
+  )
+}
+
+fn role_option() -> CreateCommandOption {
+  CreateCommandOption::new(CommandOptionType::Role, ROLE_OPTION_NAME, "Role")
+}
+
+fn user_option() -> CreateCommandOption {
+  CreateCommandOption::new(CommandOptionType::User, USER_OPTION_NAME, "User")
+}
+
+async fn list(
+  ctx: &Context,
+  command: &CommandInteraction,
+  permissions: Vec<CommandPermission>,
+) -> Result<(), Error> {
+  let lines = RESTRICTABLE
+    .iter()
+    .map(|name| {
+      let targets = permissions
+        .iter()
+        .filter(|p| p.command == *name)
+        .map(|p| p.target.mention())
+        .collect::<Vec<_>>();
+      match targets.is_empty() {
+        true => format!("**{}** - everyone", name),
+        false => format!("**{}** - {}", name, targets.join(", ")),
+      }
+    })
+    .collect::<Vec<_>>();
+
+  description_response(
+    ctx,
+    command,
+    "Command permissions",
+    format!(
+      "{}\n\nServer managers and the DJ role can always use these",
+      lines.join("\n")
+    ),
+  )
+  .await
+}
+
+async fn description_response(
+  ctx: &Context,
+  command: &CommandInteraction,
+  title: &str,
+  description: String,
+) -> Result<(), Error> {
+  match command
+    .edit_response(
+      &ctx.http,
+      EditInteractionResponse::new().embed(
+        CreateEmbed::new()
+          .title(title)
+          .colour(EMBED_COLOUR)
+          .description(description),
+      ),
+    )
+    .await
+  {
+    Ok(_m) => Ok(()),
+    Err(e) => Err(e),
+  }
+}
@@ -1,108 +1,99 @@
 use std::sync::Arc;
 
 use crate::commands::{
+  connection,
+  direct::{self, CoverArt},
   playback::{
-    format_duration, format_duration_live, get_queue_length_and_duration, get_source, SongMetadata,
-    SongMetadataKey, VOIPData,
+    enqueue_track, expand_playlist, format_duration, format_duration_live,
+    get_queue_length_and_duration, is_playlist_url, queue_footer, reposition_added, QueuePosition,
+    Requester, SongMetadata, SongMetadataKey, SourceKind, VOIPData,
   },
   text_response,
   utils::remove_md_characters,
   Command,
 };
+use crate::config::ConfigStorage;
 use crate::constants::EMBED_COLOUR;
+use crate::state::read_guild_state;
 use serenity::{
   all::ResolvedValue,
   async_trait,
   builder::{
-    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
-    CreateEmbedAuthor, CreateEmbedFooter, CreateMessage, EditInteractionResponse,
+    CreateActionRow, CreateAttachment, CreateButton, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedAuthor, EditInteractionResponse,
   },
   client::Context,
   model::application::{CommandInteraction, CommandOptionType},
-  model::id::{ChannelId, GuildId},
+  model::channel::Attachment,
+  model::id::GuildId,
   prelude::Mutex,
   Error,
 };
-use songbird::{events::Event, Call, EventContext, EventHandler, Songbird, TrackEvent};
+use songbird::{
+  events::Event, input::Compose, Call, EventContext, EventHandler, Songbird, TrackEvent,
+};
 use tracing::error;
 
 pub struct Play;
 
 const PARAM_OPTION_NAME: &str = "search";
+const FILE_OPTION_NAME: &str = "file";
+const POSITION_OPTION_NAME: &str = "position";
 
-#[async_trait]
-impl Command for Play {
-  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
-    let param = match command
-      .data
-      .options()
-      .iter()
-      .find(|o| o.name == PARAM_OPTION_NAME)
-    {
-      Some(o) => {
-        if let ResolvedValue::String(s) = o.value {
-          s.to_string()
-        } else {
-          error!("Invalid search option provided");
-          return text_response(ctx, command, "No search term or URL in request").await;
-        }
-      }
-      None => {
-        error!("No options provided");
-        return text_response(ctx, command, "No search term or URL in request").await;
-      }
-    };
-
-    let voip_data = match VOIPData::from(ctx, command).await {
-      Ok(v) => v,
-      Err(s) => return text_response(ctx, command, s).await,
-    };
+impl Play {
+  pub async fn join_call(
+    ctx: &Context,
+    command: &CommandInteraction,
+  ) -> Result<Arc<Mutex<Call>>, String> {
+    let voip_data = VOIPData::from(ctx, command).await?;
 
     let guild_id = voip_data.guild_id;
 
-    let http_client = {
-      let data = ctx.data.read().await;
-      data
-        .get::<crate::constants::HttpKey>()
-        .cloned()
-        .expect("HttpClient did not exist")
-    };
-
     let manager = match songbird::get(ctx).await {
       Some(arc) => arc.clone(),
       None => {
         error!("Error with songbird client");
-        return text_response(ctx, command, "Error getting voice client").await;
+        return Err("Error getting voice client".to_string());
       }
     };
 
     let handler_lock = match manager.get(guild_id) {
       Some(h) => {
-        if voip_data.compare_to_call(&h).await {
-          h
-        } else {
-          match join_channel(manager, voip_data).await {
-            Ok(h) => h,
-            Err(e) => return text_response(ctx, command, e).await,
-          }
-        }
+        voip_data.reconcile_call(ctx, &h).await?;
+        h
       }
-      None => match join_channel(manager, voip_data).await {
-        Ok(h) => h,
-        Err(e) => return text_response(ctx, command, e).await,
-      },
+      None => join_channel(ctx, manager, voip_data).await?,
     };
 
-    let mut source = get_source(http_client, param);
-    let metadata = SongMetadata::from_source(&mut source).await;
+    Ok(handler_lock)
+  }
+
+  pub async fn play_track(
+    ctx: &Context,
+    command: &CommandInteraction,
+    handler_lock: Arc<Mutex<Call>>,
+    source: Box<dyn Compose>,
+    mut metadata: SongMetadata,
+    cover: Option<CoverArt>,
+    position: QueuePosition,
+  ) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+    metadata.requester = Some(Requester::from_command(command));
 
     let mut handler = handler_lock.lock().await;
 
-    let handle = handler.enqueue_input(source.into()).await;
-    {
-      let mut data = handle.typemap().write().await;
-      data.insert::<SongMetadataKey>(metadata.clone());
-    }
+    let handle = enqueue_track(
+      ctx,
+      &mut handler,
+      source,
+      metadata.clone(),
+      command.channel_id,
+      guild_id,
+    )
+    .await;
     match handle.add_event(
       Event::Track(TrackEvent::Error),
       SongError {
@@ -113,48 +104,47 @@ impl Command for Play {
       Ok(_) => (),
       Err(e) => error!("Error adding SongError event: {}", e),
     }
-    let embed_title = match handler.queue().len() == 1 {
-      true => "Playing",
-      false => "Added to queue",
+    reposition_added(ctx, &handler, 1, position, command.channel_id, guild_id);
+    let embed_title = match (handler.queue().len() == 1, position) {
+      (true, _) | (_, QueuePosition::Now) => "Playing",
+      (_, QueuePosition::Next) => "Playing next",
+      (_, QueuePosition::End) => "Added to queue",
     };
 
     if handler.queue().is_empty() {
       return text_response(ctx, command, "Error playing song").await;
     }
 
-    if handler.queue().len() > 1 {
-      match handle.add_event(
-        Event::Track(TrackEvent::Play),
-        SongStart {
-          channel_id: command.channel_id,
-          guild_id,
-          ctx: ctx.clone(),
-        },
-      ) {
-        Ok(_) => (),
-        Err(e) => error!("Error adding SongStart event: {}", e),
-      }
-    }
-
     let url = metadata.url.clone().unwrap_or_default();
     let (count, duration) = get_queue_length_and_duration(&handler.queue().current_queue()).await;
+    let loop_mode = read_guild_state(ctx, guild_id, |s| s.loop_mode).await;
 
-    let user_nick = remove_md_characters(
-      command
-        .user
-        .nick_in(&ctx.http, guild_id)
-        .await
-        .unwrap_or_else(|| command.user.tag()),
-    );
+    let user_nick = user_nick(ctx, command, guild_id).await;
 
-    match command
+    // Embedded cover art has no URL of its own until it's uploaded with the response
+    let (image, attachment) = match cover {
+      Some(c) => {
+        let file_name = c.file_name();
+        (
+          format!("attachment://{}", file_name),
+          Some(CreateAttachment::bytes(c.data, file_name)),
+        )
+      }
+      None => (metadata.thumbnail.clone(), None),
+    };
+    let response = match attachment {
+      Some(a) => EditInteractionResponse::new().new_attachment(a),
+      None => EditInteractionResponse::new(),
+    };
+
+    let message = command
       .edit_response(
         &ctx.http,
-        EditInteractionResponse::new()
+        response
           .embed(
             CreateEmbed::new()
               .title(embed_title)
-              .image(metadata.thumbnail)
+              .image(image)
               .author(CreateEmbedAuthor::new(user_nick).icon_url(command.user.face()))
               .colour(EMBED_COLOUR)
               .fields(vec![
@@ -165,22 +155,234 @@ impl Command for Play {
                   true,
                 ),
               ])
-              .footer(CreateEmbedFooter::new(format!(
-                "{} songs in queue - {}",
-                count,
-                format_duration(duration)
-              ))),
+              .footer(queue_footer(count, format_duration(duration), loop_mode)),
           )
           .components(vec![CreateActionRow::Buttons(vec![
             CreateButton::new_link(url).label("Open in browser"),
           ])]),
       )
+      .await?;
+
+    let uploaded = message
+      .embeds
+      .first()
+      .and_then(|e| e.image.as_ref())
+      .map(|i| i.url.clone())
+      .filter(|u| u.starts_with("http"));
+    if let Some(url) = uploaded {
+      if let Some(m) = handle.typemap().write().await.get_mut::<SongMetadataKey>() {
+        m.thumbnail = url;
+      }
+    }
+    Ok(())
+  }
+
+  pub async fn play_attachment(
+    ctx: &Context,
+    command: &CommandInteraction,
+    attachment: &Attachment,
+    position: QueuePosition,
+  ) -> Result<(), Error> {
+    if !direct::is_audio_attachment(attachment) {
+      let text = format!("{} isn't an audio file", attachment.filename);
+      return text_response(ctx, command, text).await;
+    }
+
+    let handler_lock = match Self::join_call(ctx, command).await {
+      Ok(h) => h,
+      Err(s) => return text_response(ctx, command, s).await,
+    };
+
+    let http_client = {
+      let data = ctx.data.read().await;
+      data
+        .get::<crate::constants::HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+
+    let (metadata, cover) = direct::probe_attachment(http_client.clone(), attachment).await;
+    let source = SourceKind::Attachment.source(http_client, attachment.url.clone());
+    Self::play_track(
+      ctx,
+      command,
+      handler_lock,
+      source,
+      metadata,
+      cover,
+      position,
+    )
+    .await
+  }
+
+  pub async fn play_tracks(
+    ctx: &Context,
+    command: &CommandInteraction,
+    handler_lock: Arc<Mutex<Call>>,
+    tracks: Vec<SongMetadata>,
+    title: String,
+    position: QueuePosition,
+  ) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let thumbnail = match tracks.first() {
+      Some(t) => t.thumbnail.clone(),
+      None => return text_response(ctx, command, "No tracks to add").await,
+    };
+
+    let http_client = {
+      let data = ctx.data.read().await;
+      data
+        .get::<crate::constants::HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+
+    let requester = Requester::from_command(command);
+    let tracks = tracks
+      .into_iter()
+      .filter_map(|metadata| {
+        let source = metadata.source(http_client.clone())?;
+        let metadata = SongMetadata {
+          requester: Some(requester.clone()),
+          ..metadata
+        };
+        Some((source, metadata))
+      })
+      .collect::<Vec<_>>();
+
+    let mut handler = handler_lock.lock().await;
+    let mut added = Vec::with_capacity(tracks.len());
+    for (source, metadata) in tracks {
+      let handle = enqueue_track(
+        ctx,
+        &mut handler,
+        source,
+        metadata,
+        command.channel_id,
+        guild_id,
+      )
+      .await;
+      added.push(handle);
+    }
+
+    reposition_added(
+      ctx,
+      &handler,
+      added.len(),
+      position,
+      command.channel_id,
+      guild_id,
+    );
+    let (added_count, added_duration) = get_queue_length_and_duration(&added).await;
+    let (count, duration) = get_queue_length_and_duration(&handler.queue().current_queue()).await;
+    drop(handler);
+    let loop_mode = read_guild_state(ctx, guild_id, |s| s.loop_mode).await;
+
+    let user_nick = user_nick(ctx, command, guild_id).await;
+
+    match command
+      .edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embed(
+          CreateEmbed::new()
+            .title(title)
+            .image(thumbnail)
+            .author(CreateEmbedAuthor::new(user_nick).icon_url(command.user.face()))
+            .colour(EMBED_COLOUR)
+            .fields(vec![
+              ("Tracks", added_count.to_string(), true),
+              ("Duration", format_duration(added_duration), true),
+            ])
+            .footer(queue_footer(count, format_duration(duration), loop_mode)),
+        ),
+      )
       .await
     {
       Ok(_m) => Ok(()),
       Err(e) => Err(e),
     }
   }
+}
+
+#[async_trait]
+impl Command for Play {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let options = command.data.options();
+    let param = options.iter().find_map(|o| match o.value {
+      ResolvedValue::String(s) if o.name == PARAM_OPTION_NAME => Some(s.to_string()),
+      _ => None,
+    });
+    let file = options.iter().find_map(|o| match o.value {
+      ResolvedValue::Attachment(a) if o.name == FILE_OPTION_NAME => Some(a),
+      _ => None,
+    });
+
+    let position = command
+      .data
+      .options()
+      .iter()
+      .find(|o| o.name == POSITION_OPTION_NAME)
+      .and_then(|o| match o.value {
+        ResolvedValue::String(s) => QueuePosition::from_name(s),
+        _ => None,
+      })
+      .unwrap_or_default();
+
+    let param = match (param, file) {
+      (_, Some(file)) => return Self::play_attachment(ctx, command, file, position).await,
+      (Some(p), None) => p,
+      (None, None) => {
+        error!("No options provided");
+        return text_response(ctx, command, "No search term, URL or file in request").await;
+      }
+    };
+
+    let handler_lock = match Self::join_call(ctx, command).await {
+      Ok(h) => h,
+      Err(s) => return text_response(ctx, command, s).await,
+    };
+
+    let http_client = {
+      let data = ctx.data.read().await;
+      data
+        .get::<crate::constants::HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+
+    if is_playlist_url(&param) {
+      return play_playlist(ctx, command, handler_lock, &param, position).await;
+    }
+
+    let source_kind = SourceKind::detect(&http_client, &param).await;
+    let (source, metadata, cover) = match source_kind {
+      SourceKind::Direct => {
+        let (metadata, cover) = direct::probe(http_client.clone(), param.clone()).await;
+        (source_kind.source(http_client, param), metadata, cover)
+      }
+      _ => {
+        let mut source = source_kind.source(http_client, param);
+        let mut metadata = SongMetadata::from_source(source.as_mut()).await;
+        metadata.source_kind = source_kind;
+        (source, metadata, None)
+      }
+    };
+
+    Self::play_track(
+      ctx,
+      command,
+      handler_lock,
+      source,
+      metadata,
+      cover,
+      position,
+    )
+    .await
+  }
 
   fn name() -> &'static str {
     "play"
@@ -193,91 +395,62 @@ impl Command for Play {
         CreateCommandOption::new(
           CommandOptionType::String,
           PARAM_OPTION_NAME,
-          "Search term or a link to a Youtube video or a file",
+          "Search term or a link to a Youtube video, playlist or a file",
         )
-        .required(true),
+        .set_autocomplete(true),
       )
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::Attachment,
+        FILE_OPTION_NAME,
+        "Audio file to play instead of a search or link",
+      ))
+      .add_option(QueuePosition::ALL.iter().fold(
+        CreateCommandOption::new(
+          CommandOptionType::String,
+          POSITION_OPTION_NAME,
+          "Where to add the track, defaults to the end of the queue",
+        ),
+        |option, position| option.add_string_choice(position.name(), position.name()),
+      ))
   }
 }
 
-struct SongStart {
-  channel_id: ChannelId,
-  guild_id: GuildId,
-  ctx: Context,
-}
-
-#[async_trait]
-impl EventHandler for SongStart {
-  async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-    let handle = if let EventContext::Track(track_ctx) = ctx {
-      let (_state, handle) = track_ctx[0];
-      handle
-    } else {
-      return Some(Event::Cancel);
-    };
-
-    let metadata = SongMetadata::from_handle(handle).await;
-
-    let manager = match songbird::get(&self.ctx).await {
-      Some(arc) => arc.clone(),
-      None => {
-        error!("Error with songbird client");
-        return Some(Event::Cancel);
-      }
-    };
-
-    let handler_lock = match manager.get(self.guild_id) {
-      Some(h) => h,
-      None => {
-        error!("Error locking guild voice client");
-        return Some(Event::Cancel);
-      }
-    };
+async fn play_playlist(
+  ctx: &Context,
+  command: &CommandInteraction,
+  handler_lock: Arc<Mutex<Call>>,
+  url: &str,
+  position: QueuePosition,
+) -> Result<(), Error> {
+  let limit = {
+    let data = ctx.data.read().await;
+    data
+      .get::<ConfigStorage>()
+      .expect("No config in global storage")
+      .playlist_limit
+  };
 
-    let handler = handler_lock.lock().await;
+  let entries = match expand_playlist(url, limit).await {
+    Ok(e) => e,
+    Err(s) => return text_response(ctx, command, s).await,
+  };
 
-    let (count, duration) = get_queue_length_and_duration(&handler.queue().current_queue()).await;
+  let title = match entries.len() >= limit {
+    true => format!("Added playlist (first {} tracks)", limit),
+    false => "Added playlist".to_string(),
+  };
 
-    drop(handler);
-    let url = metadata.url.clone().unwrap_or_default();
+  Play::play_tracks(ctx, command, handler_lock, entries, title, position).await
+}
 
-    match self
-      .channel_id
-      .send_message(
-        &self.ctx.http,
-        CreateMessage::new()
-          .embed(
-            CreateEmbed::new()
-              .title("Playing")
-              .colour(EMBED_COLOUR)
-              .image(metadata.thumbnail)
-              .fields(vec![
-                ("Track", remove_md_characters(metadata.title.clone()), true),
-                (
-                  "Duration",
-                  format_duration_live(metadata.duration, &metadata.title).to_string(),
-                  true,
-                ),
-              ])
-              .footer(CreateEmbedFooter::new(format!(
-                "{} songs in queue - {}",
-                count,
-                format_duration(duration)
-              ))),
-          )
-          .components(vec![CreateActionRow::Buttons(vec![
-            CreateButton::new_link(url).label("Open in browser"),
-          ])]),
-      )
+async fn user_nick(ctx: &Context, command: &CommandInteraction, guild_id: GuildId) -> String {
+  remove_md_characters(
+    command
+      .user
+      .nick_in(&ctx.http, guild_id)
       .await
-    {
-      Ok(_o) => return None,
-      Err(e) => {
-        error!("{}", e);
-        return None;
-      }
-    }
-  }
+      .unwrap_or_else(|| command.user.tag()),
+  )
 }
 
 struct SongError {
@@ -299,10 +472,11 @@ impl EventHandler for SongError {
 }
 
 async fn join_channel(
+  ctx: &Context,
   manager: Arc<Songbird>,
   voip_data: VOIPData,
 ) -> Result<Arc<Mutex<Call>>, String> {
-  let join = manager.join(voip_data.guild_id, voip_data.channel_id).await;
+  let join = connection::join(ctx, &manager, &voip_data).await;
   match join {
     Ok(j) => Ok(j),
     Err(e) => {
@@ -0,0 +1,42 @@
+use crate::commands::{
+  cmd::Play, direct::is_audio_attachment, playback::QueuePosition, text_response, Command,
+};
+use serenity::async_trait;
+use serenity::builder::CreateCommand;
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandType, ResolvedTarget};
+use serenity::Error;
+use tracing::error;
+
+pub struct PlayThis;
+
+#[async_trait]
+impl Command for PlayThis {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let message = match command.data.target() {
+      Some(ResolvedTarget::Message(m)) => m,
+      _ => {
+        error!("No message targeted");
+        return text_response(ctx, command, "No message to play from").await;
+      }
+    };
+
+    let attachment = match message.attachments.iter().find(|a| is_audio_attachment(a)) {
+      Some(a) => a,
+      None => match message.attachments.is_empty() {
+        true => return text_response(ctx, command, "That message has no attachments").await,
+        false => return text_response(ctx, command, "That message has no audio files").await,
+      },
+    };
+
+    Play::play_attachment(ctx, command, attachment, QueuePosition::End).await
+  }
+
+  fn name() -> &'static str {
+    "Play this"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name()).kind(CommandType::Message)
+  }
+}
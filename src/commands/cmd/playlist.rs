@@ -0,0 +1,410 @@
+use crate::commands::{
+  cmd::Play,
+  playback::{QueuePosition, SongMetadata},
+  text_response,
+  utils::remove_md_characters,
+  Command,
+};
+use crate::constants::EMBED_COLOUR;
+use crate::storage::{database, PlaylistTrack, SavedPlaylist};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed, EditInteractionResponse};
+use serenity::client::Context;
+use serenity::model::application::{
+  CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+};
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::Error;
+use tracing::error;
+
+pub struct Playlist;
+
+const SAVE_SUBCOMMAND: &str = "save";
+const LOAD_SUBCOMMAND: &str = "load";
+const LIST_SUBCOMMAND: &str = "list";
+const DELETE_SUBCOMMAND: &str = "delete";
+const SHARE_SUBCOMMAND: &str = "share";
+
+const NAME_OPTION_NAME: &str = "name";
+const OWNER_OPTION_NAME: &str = "owner";
+const ENABLED_OPTION_NAME: &str = "enabled";
+
+const MAX_NAME_LENGTH: u16 = 100;
+const MAX_LISTED: usize = 30;
+
+#[async_trait]
+impl Command for Playlist {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let (subcommand, options) = match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+      }) => (name, options),
+      _ => {
+        error!("No subcommand provided");
+        return text_response(ctx, command, "Invalid subcommand").await;
+      }
+    };
+
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let owner = options.iter().find_map(|o| match o.value {
+      ResolvedValue::User(user, _) if o.name == OWNER_OPTION_NAME => Some(user),
+      _ => None,
+    });
+
+    if subcommand == LIST_SUBCOMMAND {
+      return list(ctx, command, guild_id, owner).await;
+    }
+
+    let name = match options.iter().find_map(|o| match o.value {
+      ResolvedValue::String(s) if o.name == NAME_OPTION_NAME => Some(s.trim().to_string()),
+      _ => None,
+    }) {
+      Some(n) if !n.is_empty() => n,
+      _ => return text_response(ctx, command, "No playlist name in request").await,
+    };
+
+    match subcommand {
+      SAVE_SUBCOMMAND => save(ctx, command, guild_id, name).await,
+      LOAD_SUBCOMMAND => load(ctx, command, guild_id, name, owner).await,
+      DELETE_SUBCOMMAND => delete(ctx, command, guild_id, name).await,
+      SHARE_SUBCOMMAND => {
+        let enabled = options
+          .iter()
+          .find_map(|o| match o.value {
+            ResolvedValue::Boolean(b) if o.name == ENABLED_OPTION_NAME => Some(b),
+            _ => None,
+          })
+          .unwrap_or(true);
+        share(ctx, command, guild_id, name, enabled).await
+      }
+      _ => text_response(ctx, command, "Invalid subcommand").await,
+    }
+  }
+
+  fn name() -> &'static str {
+    "playlist"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Save and load named playlists")
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          SAVE_SUBCOMMAND,
+          "Save the current queue as a playlist",
+        )
+        .add_sub_option(name_option()),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          LOAD_SUBCOMMAND,
+          "Add a saved playlist to the queue",
+        )
+        .add_sub_option(name_option())
+        .add_sub_option(owner_option("Load a playlist shared by this user")),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          LIST_SUBCOMMAND,
+          "List saved playlists",
+        )
+        .add_sub_option(owner_option("List playlists shared by this user")),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          DELETE_SUBCOMMAND,
+          "Delete one of your playlists",
+        )
+        .add_sub_option(name_option()),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          SHARE_SUBCOMMAND,
+          "Let others in the server load one of your playlists",
+        )
+        .add_sub_option(name_option())
+        .add_sub_option(CreateCommandOption::new(
+          CommandOptionType::Boolean,
+          ENABLED_OPTION_NAME,
+          "Whether the playlist is shared, defaults to true",
+        )),
+      )
+  }
+}
+
+fn name_option() -> CreateCommandOption {
+  CreateCommandOption::new(CommandOptionType::String, NAME_OPTION_NAME, "Playlist name")
+    .max_length(MAX_NAME_LENGTH)
+    .required(true)
+}
+
+fn owner_option(description: &str) -> CreateCommandOption {
+  CreateCommandOption::new(CommandOptionType::User, OWNER_OPTION_NAME, description)
+}
+
+async fn save(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  name: String,
+) -> Result<(), Error> {
+  let queue = match songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+    Some(h) => h.lock().await.queue().current_queue(),
+    None => vec![],
+  };
+
+  let mut tracks = Vec::with_capacity(queue.len());
+  let mut uploads = 0;
+  for handle in &queue {
+    let metadata = SongMetadata::from_handle(handle).await;
+    match metadata.saved_url() {
+      Some(url) => tracks.push(PlaylistTrack {
+        thumbnail: metadata.saved_thumbnail(),
+        title: metadata.title,
+        url,
+        duration: metadata.duration,
+        source_kind: Some(metadata.source_kind.name().to_string()),
+      }),
+      None if metadata.url.is_some() => uploads += 1,
+      None => (),
+    }
+  }
+
+  if tracks.is_empty() {
+    return match uploads {
+      0 => text_response(ctx, command, "Queue is empty").await,
+      _ => text_response(ctx, command, "Uploaded files can't be saved to playlists").await,
+    };
+  }
+
+  let count = tracks.len();
+  let left_out = match uploads {
+    0 => "".to_string(),
+    1 => ", leaving out 1 uploaded file since its link expires".to_string(),
+    n => format!(
+      ", leaving out {} uploaded files since their links expire",
+      n
+    ),
+  };
+  match database(ctx)
+    .await
+    .save_playlist(guild_id, command.user.id, name.clone(), tracks)
+    .await
+  {
+    Ok(_) => {
+      text_response(
+        ctx,
+        command,
+        format!("Saved {} tracks to playlist {}{}", count, name, left_out),
+      )
+      .await
+    }
+    Err(e) => {
+      error!("Error saving playlist: {}", e);
+      text_response(ctx, command, "Couldn't save playlist").await
+    }
+  }
+}
+
+async fn load(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  name: String,
+  owner: Option<&User>,
+) -> Result<(), Error> {
+  let owner_id = owner.map(|u| u.id).unwrap_or(command.user.id);
+  let database = database(ctx).await;
+
+  let playlist = match database.playlist(guild_id, owner_id, name.clone()).await {
+    Ok(Some(p)) if p.shared || owner_id == command.user.id => p,
+    Ok(_) => return text_response(ctx, command, format!("No playlist named {}", name)).await,
+    Err(e) => {
+      error!("Error reading playlist: {}", e);
+      return text_response(ctx, command, "Couldn't load playlist").await;
+    }
+  };
+
+  let saved = match database.playlist_tracks(playlist.id).await {
+    Ok(t) => t,
+    Err(e) => {
+      error!("Error reading playlist tracks: {}", e);
+      return text_response(ctx, command, "Couldn't load playlist").await;
+    }
+  };
+
+  if saved.is_empty() {
+    return text_response(ctx, command, "Playlist is empty").await;
+  }
+
+  let tracks = saved
+    .into_iter()
+    .map(SongMetadata::from_saved)
+    .collect::<Vec<_>>();
+
+  let handler_lock = match Play::join_call(ctx, command).await {
+    Ok(h) => h,
+    Err(s) => return text_response(ctx, command, s).await,
+  };
+
+  let title = format!("Added playlist {}", remove_md_characters(playlist.name));
+  Play::play_tracks(
+    ctx,
+    command,
+    handler_lock,
+    tracks,
+    title,
+    QueuePosition::End,
+  )
+  .await
+}
+
+async fn list(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  owner: Option<&User>,
+) -> Result<(), Error> {
+  let owner_id = owner.map(|u| u.id).unwrap_or(command.user.id);
+  let own = owner_id == command.user.id;
+
+  let playlists = match database(ctx).await.playlists(guild_id, owner_id).await {
+    Ok(p) => p
+      .into_iter()
+      .filter(|p| own || p.shared)
+      .collect::<Vec<_>>(),
+    Err(e) => {
+      error!("Error listing playlists: {}", e);
+      return text_response(ctx, command, "Couldn't list playlists").await;
+    }
+  };
+
+  let title = match owner {
+    Some(user) if !own => format!("Playlists shared by {}", remove_md_characters(user.tag())),
+    _ => "Your playlists".to_string(),
+  };
+
+  let description = match playlists.is_empty() {
+    true => "No playlists saved".to_string(),
+    false => format_playlists(&playlists),
+  };
+
+  match command
+    .edit_response(
+      &ctx.http,
+      EditInteractionResponse::new().embed(
+        CreateEmbed::new()
+          .title(title)
+          .colour(EMBED_COLOUR)
+          .description(description),
+      ),
+    )
+    .await
+  {
+    Ok(_m) => Ok(()),
+    Err(e) => Err(e),
+  }
+}
+
+fn format_playlists(playlists: &[SavedPlaylist]) -> String {
+  let mut lines = playlists
+    .iter()
+    .take(MAX_LISTED)
+    .map(|p| {
+      format!(
+        "**{}** - {} tracks{}",
+        remove_md_characters(p.name.clone()),
+        p.track_count,
+        if p.shared { " (shared)" } else { "" }
+      )
+    })
+    .collect::<Vec<_>>();
+
+  if playlists.len() > MAX_LISTED {
+    lines.push(format!("... and {} more", playlists.len() - MAX_LISTED));
+  }
+  lines.join("\n")
+}
+
+async fn own_playlist(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  name: &str,
+) -> Result<SavedPlaylist, String> {
+  match database(ctx)
+    .await
+    .playlist(guild_id, command.user.id, name.to_string())
+    .await
+  {
+    Ok(Some(p)) => Ok(p),
+    Ok(None) => Err(format!("You have no playlist named {}", name)),
+    Err(e) => {
+      error!("Error reading playlist: {}", e);
+      Err("Couldn't read playlist".to_string())
+    }
+  }
+}
+
+async fn delete(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  name: String,
+) -> Result<(), Error> {
+  let playlist = match own_playlist(ctx, command, guild_id, &name).await {
+    Ok(p) => p,
+    Err(s) => return text_response(ctx, command, s).await,
+  };
+
+  match database(ctx).await.delete_playlist(playlist.id).await {
+    Ok(_) => text_response(ctx, command, format!("Deleted playlist {}", name)).await,
+    Err(e) => {
+      error!("Error deleting playlist: {}", e);
+      text_response(ctx, command, "Couldn't delete playlist").await
+    }
+  }
+}
+
+async fn share(
+  ctx: &Context,
+  command: &CommandInteraction,
+  guild_id: GuildId,
+  name: String,
+  enabled: bool,
+) -> Result<(), Error> {
+  let playlist = match own_playlist(ctx, command, guild_id, &name).await {
+    Ok(p) => p,
+    Err(s) => return text_response(ctx, command, s).await,
+  };
+
+  match database(ctx)
+    .await
+    .set_playlist_shared(playlist.id, enabled)
+    .await
+  {
+    Ok(_) => {
+      let text = match enabled {
+        true => format!("Playlist {} is now shared", name),
+        false => format!("Playlist {} is no longer shared", name),
+      };
+      text_response(ctx, command, text).await
+    }
+    Err(e) => {
+      error!("Error sharing playlist: {}", e);
+      text_response(ctx, command, "Couldn't share playlist").await
+    }
+  }
+}
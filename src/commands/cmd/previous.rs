@@ -0,0 +1,117 @@
+use crate::commands::{
+  cmd::Play,
+  playback::{
+    enqueue_track, format_duration_live, reposition_added, QueuePosition, Requester, SongMetadata,
+  },
+  text_response,
+  utils::remove_md_characters,
+  Command,
+};
+use crate::constants::{HttpKey, EMBED_COLOUR};
+use crate::storage::database;
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateEmbed, EditInteractionResponse};
+use serenity::client::Context;
+use serenity::model::application::CommandInteraction;
+use serenity::Error;
+use tracing::error;
+
+pub struct Previous;
+
+#[async_trait]
+impl Command for Previous {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let handler_lock = match Play::join_call(ctx, command).await {
+      Ok(h) => h,
+      Err(s) => return text_response(ctx, command, s).await,
+    };
+
+    let track = match database(ctx).await.recent_history(guild_id, 1).await {
+      Ok(h) => match h.into_iter().next() {
+        Some(t) => t,
+        None => return text_response(ctx, command, "No previous track").await,
+      },
+      Err(e) => {
+        error!("Error reading play history: {}", e);
+        return text_response(ctx, command, "Couldn't read history").await;
+      }
+    };
+
+    let http_client = {
+      let data = ctx.data.read().await;
+      data
+        .get::<HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+
+    let metadata = SongMetadata {
+      requester: Some(Requester::from_command(command)),
+      ..SongMetadata::from_history(track)
+    };
+    let source = match metadata.source(http_client) {
+      Some(s) => s,
+      None => return text_response(ctx, command, "No previous track").await,
+    };
+
+    let mut handler = handler_lock.lock().await;
+    enqueue_track(
+      ctx,
+      &mut handler,
+      source,
+      metadata.clone(),
+      command.channel_id,
+      guild_id,
+    )
+    .await;
+    reposition_added(
+      ctx,
+      &handler,
+      1,
+      QueuePosition::Next,
+      command.channel_id,
+      guild_id,
+    );
+
+    let embed_title = match handler.queue().len() == 1 {
+      true => "Playing",
+      false => "Playing next",
+    };
+    drop(handler);
+
+    let length = format_duration_live(metadata.duration, &metadata.title);
+
+    match command
+      .edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embed(
+          CreateEmbed::new()
+            .title(embed_title)
+            .thumbnail(metadata.thumbnail)
+            .colour(EMBED_COLOUR)
+            .fields(vec![
+              ("Track", remove_md_characters(metadata.title), true),
+              ("Length", length.to_string(), true),
+            ]),
+        ),
+      )
+      .await
+    {
+      Ok(_m) => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  fn name() -> &'static str {
+    "previous"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name()).description("Queue the last finished track to play next")
+  }
+}
@@ -1,26 +1,81 @@
 use crate::commands::{
   playback::{
-    format_duration, format_duration_live, get_queue_length_and_duration, SongMetadata, VOIPData,
+    discard_track, format_duration, format_duration_live, format_volume,
+    get_queue_length_and_duration, guild_settings, measured_gain, output_length, queue_footer,
+    SongMetadata, VOIPData,
   },
   text_response,
-  utils::remove_md_characters,
+  utils::{remove_md_characters, truncate_unicode},
   Command,
 };
 use crate::constants::EMBED_COLOUR;
-use serenity::builder::{CreateCommand, CreateEmbedFooter, EditInteractionResponse};
+use crate::state::read_guild_state;
+use rand::seq::SliceRandom;
+use serenity::builder::{
+  CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+  CreateInteractionResponseMessage, EditInteractionResponse, EditMessage,
+};
 use serenity::client::Context;
-use serenity::model::application::CommandInteraction;
+use serenity::collector::ComponentInteractionCollector;
+use serenity::model::application::{
+  ButtonStyle, CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+};
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
 use serenity::Error;
 use serenity::{async_trait, builder::CreateEmbed};
-use songbird::tracks::TrackHandle;
+use songbird::tracks::{TrackHandle, TrackQueue};
 use std::time::Duration;
 use tracing::error;
 
 pub struct Queue;
 
+const VIEW_SUBCOMMAND: &str = "view";
+const REMOVE_SUBCOMMAND: &str = "remove";
+const MOVE_SUBCOMMAND: &str = "move";
+const JUMP_SUBCOMMAND: &str = "jump";
+const CLEAR_SUBCOMMAND: &str = "clear";
+const SHUFFLE_SUBCOMMAND: &str = "shuffle";
+
+const PREVIOUS_BUTTON_ID: &str = "queue_previous";
+const NEXT_BUTTON_ID: &str = "queue_next";
+const REFRESH_BUTTON_ID: &str = "queue_refresh";
+
+const PAGE_SIZE: usize = 8;
+const QUEUE_TITLE_LENGTH: usize = 37;
+// Discord's limit on an embed field's value
+const FIELD_LIMIT: usize = 1024;
+// A title line without its link, when every character needs escaping
+const PLAIN_LINE_LIMIT: usize = QUEUE_TITLE_LENGTH * 2 + "... \n".len();
+const PAGE_TIMEOUT: Duration = Duration::from_secs(120);
+
+const POSITION_OPTION_NAME: &str = "position";
+const FROM_OPTION_NAME: &str = "from";
+const TO_OPTION_NAME: &str = "to";
+
+impl Queue {
+  pub const BUTTON_ID_PREFIX: &'static str = "queue_";
+}
+
 #[async_trait]
 impl Command for Queue {
   async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let (subcommand, options) = match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+      }) => (name, options),
+      _ => {
+        error!("No subcommand provided");
+        return text_response(ctx, command, "Invalid subcommand").await;
+      }
+    };
+
+    if subcommand == VIEW_SUBCOMMAND {
+      return view(ctx, command).await;
+    }
+
     let voip_data = match VOIPData::from(ctx, command).await {
       Ok(v) => v,
       Err(s) => return text_response(ctx, command, s).await,
@@ -37,85 +92,47 @@ impl Command for Queue {
     };
 
     let handler_lock = match manager.get(guild_id) {
-      Some(h) => h,
+      Some(h) => {
+        if voip_data.compare_to_call(&h).await {
+          h
+        } else {
+          return text_response(ctx, command, "You're not in the voice channel").await;
+        }
+      }
       None => return text_response(ctx, command, "Not in a voice channel").await,
     };
 
     let handler = handler_lock.lock().await;
+    let queue = handler.queue();
 
-    if !handler.queue().is_empty() {
-      let queue = handler.queue().current_queue();
-      let (count, duration) = get_queue_length_and_duration(&queue).await;
+    if queue.is_empty() {
+      return text_response(ctx, command, "Queue is empty").await;
+    }
 
-      let current_metadata = SongMetadata::from_handle(&queue[0]).await;
+    let position = get_position(&options, POSITION_OPTION_NAME);
 
-      let current_position = match queue[0].get_info().await {
-        Ok(state) => state.position,
-        Err(e) => {
-          error!("Couldn't get track state: {}", e);
-          Duration::from_secs(0)
-        }
-      };
-
-      let current_song_duration =
-        format_duration_live(current_metadata.duration, &current_metadata.title);
-      let mut live = bool::from(&current_song_duration);
-
-      let current_song_info = format!(
-        "{} \n**[ {} / {} ]**",
-        format_with_url(
-          remove_md_characters(truncate_unicode(&current_metadata.title, 67)),
-          current_metadata.url.as_ref()
-        ),
-        format_duration(current_position),
-        current_song_duration,
-      );
-
-      let queue_f = format_queue_string(queue).await;
-
-      live = queue_f.3 || live;
-
-      let fields = match handler.queue().len() < 2 {
-        true => vec![("Currently playing: ", current_song_info, false)],
-        false => vec![
-          ("Currently playing: ", current_song_info, false),
-          ("Position", queue_f.0, true),
-          ("Track", queue_f.1, true),
-          ("Duration", queue_f.2, true),
-        ],
-      };
-
-      let time_left = match live {
-        true => "LIVE".to_string(),
-        false => format_duration(
-          duration
-            .checked_sub(current_position)
-            .unwrap_or(Duration::from_secs(0)),
-        ),
-      };
-
-      match command
-        .edit_response(
-          &ctx.http,
-          EditInteractionResponse::new().embed(
-            CreateEmbed::new()
-              .title("Queue")
-              .colour(EMBED_COLOUR)
-              .fields(fields)
-              .footer(CreateEmbedFooter::new(format!(
-                "{} songs in queue - {}",
-                count, time_left
-              ))),
-          ),
-        )
-        .await
-      {
-        Ok(_m) => Ok(()),
-        Err(e) => Err(e),
-      }
-    } else {
-      text_response(ctx, command, "Queue is empty").await
-    }
+    let text = match subcommand {
+      REMOVE_SUBCOMMAND => match position {
+        Some(p) => remove(queue, p).await,
+        None => "No position in request".to_string(),
+      },
+      MOVE_SUBCOMMAND => match (
+        get_position(&options, FROM_OPTION_NAME),
+        get_position(&options, TO_OPTION_NAME),
+      ) {
+        (Some(from), Some(to)) => move_entry(queue, from, to).await,
+        _ => "No positions in request".to_string(),
+      },
+      JUMP_SUBCOMMAND => match position {
+        Some(p) => jump(queue, p).await,
+        None => "No position in request".to_string(),
+      },
+      CLEAR_SUBCOMMAND => clear(queue).await,
+      SHUFFLE_SUBCOMMAND => shuffle(queue),
+      _ => "Invalid subcommand".to_string(),
+    };
+
+    text_response(ctx, command, text).await
   }
 
   fn name() -> &'static str {
@@ -123,10 +140,384 @@ impl Command for Queue {
   }
 
   fn info() -> CreateCommand {
-    CreateCommand::new(Self::name()).description("View currently queued songs")
+    CreateCommand::new(Self::name())
+      .description("View and edit the queue")
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        VIEW_SUBCOMMAND,
+        "View currently queued songs",
+      ))
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          REMOVE_SUBCOMMAND,
+          "Remove a song from the queue",
+        )
+        .add_sub_option(position_option(
+          POSITION_OPTION_NAME,
+          "Position of the song to remove",
+        )),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          MOVE_SUBCOMMAND,
+          "Move a song to another position in the queue",
+        )
+        .add_sub_option(position_option(
+          FROM_OPTION_NAME,
+          "Position of the song to move",
+        ))
+        .add_sub_option(position_option(
+          TO_OPTION_NAME,
+          "Position to move the song to",
+        )),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          JUMP_SUBCOMMAND,
+          "Skip ahead to a position in the queue",
+        )
+        .add_sub_option(position_option(
+          POSITION_OPTION_NAME,
+          "Position of the song to jump to",
+        )),
+      )
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        CLEAR_SUBCOMMAND,
+        "Remove every song after the current one",
+      ))
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        SHUFFLE_SUBCOMMAND,
+        "Shuffle the upcoming songs",
+      ))
+  }
+}
+
+fn position_option(name: &str, description: &str) -> CreateCommandOption {
+  CreateCommandOption::new(CommandOptionType::Integer, name, description)
+    .min_int_value(1)
+    .required(true)
+}
+
+fn get_position(options: &[ResolvedOption], name: &str) -> Option<usize> {
+  options
+    .iter()
+    .find(|o| o.name == name)
+    .and_then(|o| match o.value {
+      ResolvedValue::Integer(i) => usize::try_from(i).ok(),
+      _ => None,
+    })
+}
+
+async fn remove(queue: &TrackQueue, position: usize) -> String {
+  if position == 0 {
+    return "Use /skip to remove the current song".to_string();
+  }
+
+  match queue.dequeue(position) {
+    Some(track) => {
+      let metadata = SongMetadata::from_handle(&track).await;
+      discard_track(&track).await;
+      format!("Removed #{} {}", position, metadata.title)
+    }
+    None => format!("No song at #{}", position),
+  }
+}
+
+async fn move_entry(queue: &TrackQueue, from: usize, to: usize) -> String {
+  let len = queue.len();
+  if from == 0 || to == 0 {
+    return "Can't move the current song".to_string();
+  }
+  if from >= len || to >= len {
+    return format!(
+      "Positions must be between #1 and #{}",
+      len.saturating_sub(1)
+    );
+  }
+
+  let moved = queue.modify_queue(|q| {
+    let track = q.remove(from)?;
+    let handle = track.handle();
+    q.insert(to, track);
+    Some(handle)
+  });
+
+  match moved {
+    Some(handle) => {
+      let metadata = SongMetadata::from_handle(&handle).await;
+      format!("Moved {} from #{} to #{}", metadata.title, from, to)
+    }
+    None => format!("No song at #{}", from),
+  }
+}
+
+async fn jump(queue: &TrackQueue, position: usize) -> String {
+  if position == 0 || position >= queue.len() {
+    return format!("No song at #{}", position);
+  }
+
+  let skipped = queue.modify_queue(|q| q.drain(1..position).collect::<Vec<_>>());
+  for track in skipped {
+    discard_track(&track).await;
+  }
+
+  let target = queue.current_queue().get(1).cloned();
+  if let Err(e) = queue.skip() {
+    error!("Error skipping track: {}", e);
+    return "Couldn't jump in the queue".to_string();
+  }
+
+  match target {
+    Some(handle) => {
+      let metadata = SongMetadata::from_handle(&handle).await;
+      format!("Jumped to {}", metadata.title)
+    }
+    None => format!("Jumped to #{}", position),
+  }
+}
+
+async fn clear(queue: &TrackQueue) -> String {
+  let removed = queue.modify_queue(|q| q.drain(1..).collect::<Vec<_>>());
+  let count = removed.len();
+  for track in removed {
+    discard_track(&track).await;
+  }
+
+  match count {
+    0 => "No upcoming songs to clear".to_string(),
+    1 => "Cleared 1 song from the queue".to_string(),
+    n => format!("Cleared {} songs from the queue", n),
   }
 }
 
+fn shuffle(queue: &TrackQueue) -> String {
+  let count = queue.modify_queue(|q| {
+    let mut upcoming = q.drain(1..).collect::<Vec<_>>();
+    upcoming.shuffle(&mut rand::thread_rng());
+    let count = upcoming.len();
+    q.extend(upcoming);
+    count
+  });
+
+  match count {
+    0 => "No upcoming songs to shuffle".to_string(),
+    n => format!("Shuffled {} songs", n),
+  }
+}
+
+async fn view(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+  let voip_data = match VOIPData::from(ctx, command).await {
+    Ok(v) => v,
+    Err(s) => return text_response(ctx, command, s).await,
+  };
+
+  let guild_id = voip_data.guild_id;
+
+  let manager = match songbird::get(ctx).await {
+    Some(arc) => arc.clone(),
+    None => {
+      error!("Error with songbird client");
+      return text_response(ctx, command, "Error getting voice client").await;
+    }
+  };
+
+  let handler_lock = match manager.get(guild_id) {
+    Some(h) => h,
+    None => return text_response(ctx, command, "Not in a voice channel").await,
+  };
+
+  let queue = handler_lock.lock().await.queue().current_queue();
+
+  if queue.is_empty() {
+    return text_response(ctx, command, "Queue is empty").await;
+  }
+
+  let embed = queue_embed(ctx, guild_id, &queue, 0).await;
+  let message = command
+    .edit_response(
+      &ctx.http,
+      EditInteractionResponse::new()
+        .embed(embed)
+        .components(page_buttons(0, page_count(queue.len()))),
+    )
+    .await?;
+
+  tokio::spawn(paginate(ctx.clone(), guild_id, message));
+  Ok(())
+}
+
+async fn paginate(ctx: Context, guild_id: GuildId, mut message: Message) {
+  let mut page: usize = 0;
+
+  while let Some(interaction) = ComponentInteractionCollector::new(&ctx.shard)
+    .message_id(message.id)
+    .timeout(PAGE_TIMEOUT)
+    .await
+  {
+    let queue = match songbird::get(&ctx).await.and_then(|m| m.get(guild_id)) {
+      Some(h) => h.lock().await.queue().current_queue(),
+      None => vec![],
+    };
+    let pages = page_count(queue.len());
+
+    page = match interaction.data.custom_id.as_str() {
+      PREVIOUS_BUTTON_ID => page.saturating_sub(1),
+      NEXT_BUTTON_ID => page + 1,
+      _ => page,
+    }
+    .min(pages - 1);
+
+    let response = match queue.is_empty() {
+      true => CreateInteractionResponseMessage::new()
+        .embed(
+          CreateEmbed::new()
+            .title("Queue is empty")
+            .colour(EMBED_COLOUR),
+        )
+        .components(vec![]),
+      false => CreateInteractionResponseMessage::new()
+        .embed(queue_embed(&ctx, guild_id, &queue, page).await)
+        .components(page_buttons(page, pages)),
+    };
+
+    if let Err(e) = interaction
+      .create_response(
+        &ctx.http,
+        CreateInteractionResponse::UpdateMessage(response),
+      )
+      .await
+    {
+      error!("Error updating queue page: {}", e);
+    }
+  }
+
+  if let Err(e) = message
+    .edit(&ctx.http, EditMessage::new().components(vec![]))
+    .await
+  {
+    error!("Error removing queue buttons: {}", e);
+  }
+}
+
+fn page_count(queue_len: usize) -> usize {
+  queue_len.saturating_sub(1).div_ceil(PAGE_SIZE).max(1)
+}
+
+fn page_buttons(page: usize, pages: usize) -> Vec<CreateActionRow> {
+  vec![CreateActionRow::Buttons(vec![
+    CreateButton::new(PREVIOUS_BUTTON_ID)
+      .label("Previous")
+      .style(ButtonStyle::Secondary)
+      .disabled(page == 0),
+    CreateButton::new(NEXT_BUTTON_ID)
+      .label("Next")
+      .style(ButtonStyle::Secondary)
+      .disabled(page + 1 >= pages),
+    CreateButton::new(REFRESH_BUTTON_ID)
+      .label("Refresh")
+      .style(ButtonStyle::Primary),
+  ])]
+}
+
+async fn queue_embed(
+  ctx: &Context,
+  guild_id: GuildId,
+  queue: &[TrackHandle],
+  page: usize,
+) -> CreateEmbed {
+  // Positions count output time, so lengths follow the speed preset too
+  let (count, duration) = get_queue_length_and_duration(queue).await;
+  let duration = output_length(ctx, guild_id, duration).await;
+
+  let current_metadata = SongMetadata::from_handle(&queue[0]).await;
+
+  let current_position = match queue[0].get_info().await {
+    Ok(state) => state.position,
+    Err(e) => {
+      error!("Couldn't get track state: {}", e);
+      Duration::from_secs(0)
+    }
+  };
+
+  let current_song_duration = format_duration_live(
+    output_length(ctx, guild_id, current_metadata.duration).await,
+    &current_metadata.title,
+  );
+
+  let mut current_song_info = format!(
+    "{} \n**[ {} / {} ]**",
+    format_with_url(
+      remove_md_characters(truncate_unicode(&current_metadata.title, 67)),
+      current_metadata.url.as_ref()
+    ),
+    format_duration(current_position),
+    current_song_duration,
+  );
+  if let Some(requested_by) = current_metadata.requested_by() {
+    current_song_info.push_str(&format!("\nRequested by {}", requested_by));
+  }
+  let settings = guild_settings(ctx, guild_id).await;
+  current_song_info.push_str(&format!(
+    "\nVolume: {}",
+    format_volume(current_metadata.volume, settings.volume)
+  ));
+  if let Some(gain) = measured_gain(&queue[0]).await {
+    current_song_info.push_str(&format!(
+      "\nLoudness gain: {:+.1} dB{}",
+      gain,
+      if settings.normalize {
+        ""
+      } else {
+        " (normalisation off)"
+      }
+    ));
+  }
+
+  let mut live = bool::from(&current_song_duration);
+  for handle in queue.iter().skip(1) {
+    let metadata = SongMetadata::from_handle(handle).await;
+    live = bool::from(&format_duration_live(metadata.duration, &metadata.title)) || live;
+  }
+
+  let queue_f = format_queue_string(queue, page).await;
+
+  let fields = match queue.len() < 2 {
+    true => vec![("Currently playing: ", current_song_info, false)],
+    false => vec![
+      ("Currently playing: ", current_song_info, false),
+      ("Position", queue_f.0, true),
+      ("Track", queue_f.1, true),
+      ("Duration / Requested by", queue_f.2, true),
+    ],
+  };
+
+  let time_left = match live {
+    true => "LIVE".to_string(),
+    false => format_duration(
+      duration
+        .checked_sub(current_position)
+        .unwrap_or(Duration::from_secs(0)),
+    ),
+  };
+
+  let loop_mode = read_guild_state(ctx, guild_id, |s| s.loop_mode).await;
+
+  CreateEmbed::new()
+    .title(format!(
+      "Queue - Page {}/{}",
+      page + 1,
+      page_count(queue.len())
+    ))
+    .colour(EMBED_COLOUR)
+    .fields(fields)
+    .footer(queue_footer(count, time_left, loop_mode))
+}
+
 fn format_with_url(title: String, url: Option<&String>) -> String {
   if let Some(link) = url {
     format!("[{}]({})", title, link)
@@ -135,36 +526,39 @@ fn format_with_url(title: String, url: Option<&String>) -> String {
   }
 }
 
-fn truncate_unicode(text: &str, max_chars: usize) -> String {
-  match text.char_indices().nth(max_chars) {
-    None => text.to_string(),
-    Some((i, _)) => {
-      let mut valid_index = i;
-      while !text.is_char_boundary(valid_index) {
-        valid_index -= 1;
-      }
-      let trimmed = &text[..valid_index];
-      format!("{}...", trimmed)
-    }
-  }
-}
-
-async fn format_queue_string(queue: Vec<TrackHandle>) -> (String, String, String, bool) {
+async fn format_queue_string(queue: &[TrackHandle], page: usize) -> (String, String, String) {
   let mut pos_out = "".to_string();
   let mut title_out = "".to_string();
   let mut duration_out = "".to_string();
-  let mut live = false;
-  for (i, handle) in queue.iter().enumerate().skip(1).take(4) {
+  let entries = queue
+    .iter()
+    .enumerate()
+    .skip(1 + page * PAGE_SIZE)
+    .take(PAGE_SIZE)
+    .collect::<Vec<_>>();
+  for (line, (i, handle)) in entries.iter().enumerate() {
     let metadata = SongMetadata::from_handle(handle).await;
-    let title_trimmed = truncate_unicode(&metadata.title, 37);
-    let title = format_with_url(remove_md_characters(title_trimmed), metadata.url.as_ref());
+    let title_trimmed = remove_md_characters(truncate_unicode(&metadata.title, QUEUE_TITLE_LENGTH));
+    // Long links like uploads' would push the field over its limit, so keep room for the lines after
+    let linked = format_with_url(title_trimmed.clone(), metadata.url.as_ref());
+    let reserved = (entries.len() - line - 1) * PLAIN_LINE_LIMIT;
+    let length = title_out.chars().count() + linked.chars().count() + " \n".len();
+    let title = match length + reserved <= FIELD_LIMIT {
+      true => linked,
+      false => title_trimmed,
+    };
 
-    let duration = format_duration_live(metadata.duration, &metadata.title);
-    live = bool::from(&duration) || live;
+    let mut duration = format_duration_live(metadata.duration, &metadata.title).to_string();
+    if let Some(requester) = metadata.requester {
+      duration.push_str(&format!(
+        " - {}",
+        remove_md_characters(truncate_unicode(&requester.display_name, 13))
+      ));
+    }
 
     pos_out.push_str(format!("#{} \n", i).as_str());
     title_out.push_str(format!("{} \n", title).as_str());
     duration_out.push_str(format!("{} \n", duration).as_str());
   }
-  (pos_out, title_out, duration_out, live)
+  (pos_out, title_out, duration_out)
 }
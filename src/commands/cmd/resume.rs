@@ -11,10 +11,28 @@ use serenity::{
   async_trait,
   builder::{CreateEmbed, EditInteractionResponse},
 };
+use songbird::{tracks::TrackHandle, Call};
 use tracing::error;
 
 pub struct Resume;
 
+impl Resume {
+  pub fn resume_current(call: &Call) -> Result<TrackHandle, &'static str> {
+    let current = match call.queue().current() {
+      Some(t) => t,
+      None => return Err("Nothing is paused"),
+    };
+
+    match current.play() {
+      Err(e) => {
+        error!("Error resuming track: {}", e);
+        Err("Could not resume")
+      }
+      Ok(_) => Ok(current),
+    }
+  }
+}
+
 #[async_trait]
 impl Command for Resume {
   async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
@@ -35,31 +53,19 @@ impl Command for Resume {
 
     let handler_lock = match manager.get(guild_id) {
       Some(h) => {
-        if voip_data.compare_to_call(&h).await {
-          h
-        } else {
-          return text_response(ctx, command, "You're not in the voice channel").await;
+        if let Err(s) = voip_data.reconcile_call(ctx, &h).await {
+          return text_response(ctx, command, s).await;
         }
+        h
       }
       None => return text_response(ctx, command, "Not in a voice channel").await,
     };
 
     let handler = handler_lock.lock().await;
 
-    if handler.queue().is_empty() {
-      return text_response(ctx, command, "Nothing is paused").await;
-    }
-    let current = match handler.queue().current() {
-      Some(t) => t,
-      None => return text_response(ctx, command, "Nothing is paused").await,
-    };
-
-    match current.play() {
-      Err(e) => {
-        error!("Error resuming track: {}", e);
-        text_response(ctx, command, "Could not resume").await
-      }
-      Ok(_) => {
+    match Self::resume_current(&handler) {
+      Err(s) => text_response(ctx, command, s).await,
+      Ok(current) => {
         let metadata = SongMetadata::from_handle(&current).await;
         let title = metadata.title.clone();
 
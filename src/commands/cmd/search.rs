@@ -0,0 +1,250 @@
+use crate::commands::{
+  cmd::Play,
+  playback::{format_duration_live, QueuePosition, SongMetadata, SourceKind},
+  text_response,
+  utils::{remove_md_characters, truncate_unicode},
+  Command,
+};
+use crate::constants::{HttpKey, EMBED_COLOUR};
+use serenity::async_trait;
+use serenity::builder::{
+  CreateActionRow, CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+  CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse,
+};
+use serenity::client::Context;
+use serenity::collector::ComponentInteractionCollector;
+use serenity::model::application::{
+  CommandInteraction, CommandOptionType, ComponentInteractionDataKind, ResolvedValue,
+};
+use serenity::model::channel::Message;
+use serenity::Error;
+use songbird::input::{AuxMetadata, YoutubeDl};
+use std::time::Duration;
+use tracing::error;
+
+pub struct Search;
+
+const QUERY_OPTION_NAME: &str = "query";
+const RESULTS_OPTION_NAME: &str = "results";
+const SELECT_MENU_ID: &str = "search_select";
+
+const DEFAULT_RESULTS: usize = 5;
+const MAX_RESULTS: usize = 10;
+const SELECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+impl Search {
+  pub const COMPONENT_ID_PREFIX: &'static str = "search_";
+}
+
+#[async_trait]
+impl Command for Search {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let options = command.data.options();
+
+    let query = match options.iter().find(|o| o.name == QUERY_OPTION_NAME) {
+      Some(o) => {
+        if let ResolvedValue::String(s) = o.value {
+          s.to_string()
+        } else {
+          error!("Invalid query option provided");
+          return text_response(ctx, command, "No search term in request").await;
+        }
+      }
+      None => {
+        error!("No options provided");
+        return text_response(ctx, command, "No search term in request").await;
+      }
+    };
+
+    let result_count = options
+      .iter()
+      .find(|o| o.name == RESULTS_OPTION_NAME)
+      .and_then(|o| match o.value {
+        ResolvedValue::Integer(i) => usize::try_from(i).ok(),
+        _ => None,
+      })
+      .unwrap_or(DEFAULT_RESULTS)
+      .clamp(1, MAX_RESULTS);
+
+    let http_client = {
+      let data = ctx.data.read().await;
+      data
+        .get::<HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+
+    let results = match YoutubeDl::new_search(http_client, query.clone())
+      .search(Some(result_count))
+      .await
+    {
+      Ok(r) => Vec::from_iter(r),
+      Err(e) => {
+        error!("Error searching: {}", e);
+        return text_response(ctx, command, "Couldn't search").await;
+      }
+    };
+
+    if results.is_empty() {
+      return text_response(ctx, command, "No results found").await;
+    }
+
+    let description = results
+      .iter()
+      .enumerate()
+      .map(|(i, r)| {
+        let title = r.title.clone().unwrap_or_else(|| "N/A".to_string());
+        let duration = format_duration_live(r.duration.unwrap_or_default(), &title);
+        format!(
+          "**{}.** {} ({})",
+          i + 1,
+          remove_md_characters(truncate_unicode(&title, 67)),
+          duration
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n");
+
+    let options = results
+      .iter()
+      .enumerate()
+      .map(|(i, r)| {
+        let title = r.title.clone().unwrap_or_else(|| "N/A".to_string());
+        let duration = format_duration_live(r.duration.unwrap_or_default(), &title);
+        let channel = r.channel.clone().unwrap_or_else(|| "Unknown".to_string());
+        CreateSelectMenuOption::new(
+          truncate_unicode(&format!("{}. {}", i + 1, title), 97),
+          i.to_string(),
+        )
+        .description(truncate_unicode(&format!("{} - {}", duration, channel), 97))
+      })
+      .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::new()
+      .title(truncate_unicode(&format!("Results for \"{}\"", query), 200))
+      .colour(EMBED_COLOUR)
+      .description(description);
+    if let Some(thumbnail) = results[0].thumbnail.clone() {
+      embed = embed.thumbnail(thumbnail);
+    }
+
+    let message = command
+      .edit_response(
+        &ctx.http,
+        EditInteractionResponse::new()
+          .embed(embed)
+          .components(vec![CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(SELECT_MENU_ID, CreateSelectMenuKind::String { options })
+              .placeholder("Pick a track to queue"),
+          )]),
+      )
+      .await?;
+
+    tokio::spawn(await_selection(
+      ctx.clone(),
+      command.clone(),
+      message,
+      results,
+    ));
+    Ok(())
+  }
+
+  fn name() -> &'static str {
+    "search"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Search YouTube and pick a result to play")
+      .add_option(
+        CreateCommandOption::new(CommandOptionType::String, QUERY_OPTION_NAME, "Search term")
+          .required(true),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::Integer,
+          RESULTS_OPTION_NAME,
+          "Number of results to show",
+        )
+        .min_int_value(1)
+        .max_int_value(MAX_RESULTS as u64)
+        .required(false),
+      )
+  }
+}
+
+async fn await_selection(
+  ctx: Context,
+  command: CommandInteraction,
+  message: Message,
+  results: Vec<AuxMetadata>,
+) {
+  let interaction = ComponentInteractionCollector::new(&ctx.shard)
+    .message_id(message.id)
+    .author_id(command.user.id)
+    .timeout(SELECT_TIMEOUT)
+    .await;
+
+  if let Err(e) = command
+    .edit_response(&ctx.http, EditInteractionResponse::new().components(vec![]))
+    .await
+  {
+    error!("Error removing search menu: {}", e);
+  }
+
+  let interaction = match interaction {
+    Some(i) => i,
+    None => return,
+  };
+
+  if let Err(e) = interaction
+    .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+    .await
+  {
+    error!("Error acknowledging search selection: {}", e);
+  }
+
+  let selected = match &interaction.data.kind {
+    ComponentInteractionDataKind::StringSelect { values } => values
+      .first()
+      .and_then(|v| v.parse::<usize>().ok())
+      .and_then(|i| results.get(i)),
+    _ => None,
+  };
+
+  let result = match selected.and_then(|r| r.source_url.clone().map(|u| (r, u))) {
+    Some((metadata, url)) => {
+      let http_client = {
+        let data = ctx.data.read().await;
+        data
+          .get::<HttpKey>()
+          .cloned()
+          .expect("HttpClient did not exist")
+      };
+
+      match Play::join_call(&ctx, &command).await {
+        Ok(handler_lock) => {
+          Play::play_track(
+            &ctx,
+            &command,
+            handler_lock,
+            Box::new(YoutubeDl::new(http_client, url)),
+            SongMetadata {
+              source_kind: SourceKind::Search,
+              ..SongMetadata::from_aux(metadata)
+            },
+            None,
+            QueuePosition::End,
+          )
+          .await
+        }
+        Err(s) => text_response(&ctx, &command, s).await,
+      }
+    }
+    None => text_response(&ctx, &command, "Invalid selection").await,
+  };
+
+  if let Err(e) = result {
+    error!("Couldn't queue search result: {}", e);
+  }
+}
@@ -1,15 +1,45 @@
-use crate::commands::playback::{SongMetadata, VOIPData};
-use crate::commands::{text_response, Command};
+use crate::commands::playback::{
+  format_duration, format_duration_live, output_length, SongMetadata, VOIPData,
+};
+use crate::commands::{text_response, utils::parse_duration, Command};
 use serenity::async_trait;
 use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::client::Context;
 use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
 use serenity::Error;
+use std::time::Duration;
 use tracing::error;
 
 pub struct Seek;
 
-const SECONDS_OPTION_NAME: &str = "seconds";
+const TIME_OPTION_NAME: &str = "time";
+
+enum SeekTarget {
+  Absolute(Duration),
+  Forward(Duration),
+  Backward(Duration),
+  Percent(f64),
+}
+
+impl SeekTarget {
+  fn parse(input: &str) -> Option<Self> {
+    let input = input.trim();
+    if let Some(percent) = input.strip_suffix('%') {
+      let percent = percent.trim().parse::<f64>().ok()?;
+      return match (0.0..=100.0).contains(&percent) {
+        true => Some(SeekTarget::Percent(percent)),
+        false => None,
+      };
+    }
+    if let Some(offset) = input.strip_prefix('+') {
+      return parse_duration(offset).map(SeekTarget::Forward);
+    }
+    if let Some(offset) = input.strip_prefix('-') {
+      return parse_duration(offset).map(SeekTarget::Backward);
+    }
+    parse_duration(input).map(SeekTarget::Absolute)
+  }
+}
 
 #[async_trait]
 impl Command for Seek {
@@ -19,14 +49,24 @@ impl Command for Seek {
       Err(s) => return text_response(ctx, command, s).await,
     };
 
-    let timestamp = match command
+    let target = match command
       .data
       .options()
       .iter()
-      .find(|e| e.name == SECONDS_OPTION_NAME)
+      .find(|e| e.name == TIME_OPTION_NAME)
     {
       Some(o) => match o.value {
-        ResolvedValue::Number(time) => std::time::Duration::from_secs_f64(time),
+        ResolvedValue::String(time) => match SeekTarget::parse(time) {
+          Some(t) => t,
+          None => {
+            return text_response(
+              ctx,
+              command,
+              "Malformed timestamp provided, try 1:23, 1h2m3s, +30, -15 or 50%",
+            )
+            .await
+          }
+        },
         _ => {
           error!("Invalid option type");
           return text_response(ctx, command, "Malformed timestamp provided").await;
@@ -70,43 +110,51 @@ impl Command for Seek {
       None => return text_response(ctx, command, "Nothing playing").await,
     };
 
+    // Positions count output time, so the length has to as well while a speed preset is on
     let metadata = SongMetadata::from_handle(&current).await;
-    let current_duration = metadata.duration;
-
-    if current_duration > std::time::Duration::default() && timestamp >= current_duration {
-      return text_response(
-        ctx,
-        command,
-        format!(
-          "Cannot seek past song's end (max: {}s)",
-          current_duration.as_secs()
-        ),
-      )
-      .await;
-    }
+    let current_duration = output_length(ctx, guild_id, metadata.duration).await;
 
     let current_position = match current.get_info().await {
       Ok(state) => state.position,
       Err(e) => {
         error!("Couldn't get TrackState: {}", e);
-        std::time::Duration::default()
+        Duration::default()
       }
     };
 
-    if timestamp <= current_position {
+    let timestamp = match target {
+      SeekTarget::Absolute(t) => t,
+      SeekTarget::Forward(offset) => match current_position.checked_add(offset) {
+        Some(t) => t,
+        None => return text_response(ctx, command, "Invalid time").await,
+      },
+      SeekTarget::Backward(offset) => current_position.saturating_sub(offset),
+      SeekTarget::Percent(_) if current_duration.is_zero() => {
+        return text_response(ctx, command, "Track length is unknown").await
+      }
+      SeekTarget::Percent(percent) => current_duration.mul_f64(percent / 100.0),
+    };
+
+    if !current_duration.is_zero() && timestamp >= current_duration {
       return text_response(
         ctx,
         command,
         format!(
-          "Cannot seek backwards (current: {}s)",
-          current_position.as_secs()
+          "Cannot seek past song's end (length: {})",
+          format_duration(current_duration)
         ),
       )
       .await;
     }
 
+    let position = match timestamp.is_zero() {
+      true => "0s".to_string(),
+      false => format_duration(timestamp),
+    };
+    let length = format_duration_live(current_duration, &metadata.title);
+
     match current.seek(timestamp).result_async().await {
-      Ok(_) => text_response(ctx, command, format!("Seeked to {:?}", timestamp)).await,
+      Ok(_) => text_response(ctx, command, format!("Seeked to {} / {}", position, length)).await,
       Err(e) => {
         error!("Error while seeking: {}", e);
         text_response(ctx, command, "Unknown error seeking").await
@@ -123,9 +171,9 @@ impl Command for Seek {
       .description("Seek the currently playing song")
       .add_option(
         CreateCommandOption::new(
-          CommandOptionType::Number,
-          SECONDS_OPTION_NAME,
-          "Jump to this point in the audio",
+          CommandOptionType::String,
+          TIME_OPTION_NAME,
+          "Position like 1:23 or 1h2m3s, offset like +30 or -15, or a percentage like 50%",
         )
         .required(true),
       )
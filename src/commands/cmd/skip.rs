@@ -1,20 +1,237 @@
 use crate::commands::{
-  playback::{format_duration_live, SongMetadata, VOIPData},
+  component_text_response, permissions,
+  playback::{format_duration_live, guild_settings, listener_count, SongMetadata, VOIPData},
   text_response, Command,
 };
 use crate::constants::EMBED_COLOUR;
-use serenity::builder::CreateCommand;
+use crate::state::{modify_guild_state, SkipVote};
+use serenity::builder::{
+  CreateActionRow, CreateButton, CreateCommand, CreateInteractionResponse,
+  CreateInteractionResponseMessage,
+};
 use serenity::client::Context;
-use serenity::model::application::CommandInteraction;
+use serenity::model::application::{ButtonStyle, CommandInteraction, ComponentInteraction};
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, UserId};
 use serenity::Error;
 use serenity::{
   async_trait,
   builder::{CreateEmbed, EditInteractionResponse},
 };
+use songbird::{tracks::TrackHandle, Call};
+use std::collections::HashSet;
 use tracing::error;
 
 pub struct Skip;
 
+pub enum SkipOutcome {
+  Skipped(TrackHandle),
+  Voted {
+    current: TrackHandle,
+    votes: usize,
+    required: usize,
+  },
+}
+
+impl Skip {
+  pub const BUTTON_ID_PREFIX: &'static str = "skip_vote_";
+
+  pub fn skip_current(call: &Call) -> Result<TrackHandle, &'static str> {
+    let current = match call.queue().current() {
+      Some(t) => t,
+      None => return Err("Nothing to skip"),
+    };
+
+    match call.queue().skip() {
+      Err(e) => {
+        error!("Error skipping track: {}", e);
+        Err("Nothing to skip")
+      }
+      Ok(_) => Ok(current),
+    }
+  }
+
+  // Skips straight away when voting is off or the member is a DJ or the requester, otherwise counts their vote
+  pub async fn vote(
+    ctx: &Context,
+    guild_id: GuildId,
+    member: Option<&Member>,
+    user_id: UserId,
+    call: &Call,
+  ) -> Result<SkipOutcome, &'static str> {
+    let current = match call.queue().current() {
+      Some(t) => t,
+      None => return Err("Nothing to skip"),
+    };
+
+    let settings = guild_settings(ctx, guild_id).await;
+    let requester = SongMetadata::from_handle(&current)
+      .await
+      .requester
+      .is_some_and(|r| r.user_id == user_id);
+    let dj = match (settings.dj_role_id, member) {
+      (Some(role_id), Some(member)) => member.roles.contains(&role_id),
+      _ => false,
+    };
+    if settings.skip_vote_percent == 0 || requester || dj {
+      return Self::skip_current(call).map(SkipOutcome::Skipped);
+    }
+
+    let listeners = listener_count(ctx, guild_id, call);
+    let required = (listeners * usize::from(settings.skip_vote_percent))
+      .div_ceil(100)
+      .max(1);
+
+    let track_id = current.uuid().as_u128();
+    let votes = modify_guild_state(ctx, guild_id, |s| {
+      if s.skip_vote.as_ref().is_some_and(|v| v.track_id != track_id) {
+        s.skip_vote = None;
+      }
+      let vote = s.skip_vote.get_or_insert_with(|| SkipVote {
+        track_id,
+        voters: HashSet::new(),
+      });
+      vote.voters.insert(user_id);
+      vote.voters.len()
+    })
+    .await;
+
+    if votes >= required {
+      return Self::skip_current(call).map(SkipOutcome::Skipped);
+    }
+    Ok(SkipOutcome::Voted {
+      current,
+      votes,
+      required,
+    })
+  }
+
+  pub async fn handle_vote_button(
+    ctx: &Context,
+    component: &ComponentInteraction,
+  ) -> Result<(), Error> {
+    let guild_id = match component.guild_id {
+      Some(g) => g,
+      None => {
+        return component_text_response(ctx, component, "Error getting guild information").await
+      }
+    };
+
+    if let Some(member) = &component.member {
+      if let Err(reason) =
+        permissions::check_rules(ctx, guild_id, member, component.user.id, Self::name()).await
+      {
+        return permissions::deny_component(ctx, component, reason).await;
+      }
+    }
+
+    let voip_data = match VOIPData::from_user(ctx, guild_id, component.user.id) {
+      Ok(v) => v,
+      Err(s) => return component_text_response(ctx, component, s).await,
+    };
+
+    let handler_lock = match songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+      Some(h) => {
+        if voip_data.compare_to_call(&h).await {
+          h
+        } else {
+          return component_text_response(ctx, component, "You're not in the voice channel").await;
+        }
+      }
+      None => return component_text_response(ctx, component, "Not in a voice channel").await,
+    };
+
+    let track_id = component
+      .data
+      .custom_id
+      .strip_prefix(Self::BUTTON_ID_PREFIX)
+      .and_then(|id| u128::from_str_radix(id, 16).ok());
+
+    let handler = handler_lock.lock().await;
+    let current_id = handler.queue().current().map(|t| t.uuid().as_u128());
+    if track_id.is_none() || track_id != current_id {
+      drop(handler);
+      return component_text_response(ctx, component, "That track is no longer playing").await;
+    }
+
+    let outcome = Self::vote(
+      ctx,
+      guild_id,
+      component.member.as_ref(),
+      component.user.id,
+      &handler,
+    )
+    .await;
+    drop(handler);
+
+    let response = match outcome {
+      Err(s) => return component_text_response(ctx, component, s).await,
+      Ok(SkipOutcome::Skipped(current)) => CreateInteractionResponseMessage::new()
+        .embed(skipped_embed(&SongMetadata::from_handle(&current).await))
+        .components(vec![]),
+      Ok(SkipOutcome::Voted {
+        current,
+        votes,
+        required,
+      }) => {
+        let (embed, components) = vote_embed(&current, votes, required).await;
+        CreateInteractionResponseMessage::new()
+          .embed(embed)
+          .components(components)
+      }
+    };
+
+    component
+      .create_response(
+        &ctx.http,
+        CreateInteractionResponse::UpdateMessage(response),
+      )
+      .await
+  }
+}
+
+fn skipped_embed(metadata: &SongMetadata) -> CreateEmbed {
+  let length = format_duration_live(metadata.duration, &metadata.title);
+
+  let mut fields = vec![
+    ("Track", metadata.title.clone(), true),
+    ("Length", length.to_string(), true),
+  ];
+  if let Some(requested_by) = metadata.requested_by() {
+    fields.push(("Requested by", requested_by, false));
+  }
+
+  CreateEmbed::new()
+    .title("Skipped")
+    .colour(EMBED_COLOUR)
+    .fields(fields)
+}
+
+async fn vote_embed(
+  current: &TrackHandle,
+  votes: usize,
+  required: usize,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+  let metadata = SongMetadata::from_handle(current).await;
+  let embed = CreateEmbed::new()
+    .title("Vote to skip")
+    .colour(EMBED_COLOUR)
+    .fields(vec![
+      ("Track", metadata.title, true),
+      ("Votes", format!("{}/{}", votes, required), true),
+    ]);
+
+  let button = CreateButton::new(format!(
+    "{}{:x}",
+    Skip::BUTTON_ID_PREFIX,
+    current.uuid().as_u128()
+  ))
+  .label("Vote to skip")
+  .style(ButtonStyle::Primary);
+
+  (embed, vec![CreateActionRow::Buttons(vec![button])])
+}
+
 #[async_trait]
 impl Command for Skip {
   async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
@@ -35,56 +252,44 @@ impl Command for Skip {
 
     let handler_lock = match manager.get(guild_id) {
       Some(h) => {
-        if voip_data.compare_to_call(&h).await {
-          h
-        } else {
-          return text_response(ctx, command, "You're not in the voice channel").await;
+        if let Err(s) = voip_data.reconcile_call(ctx, &h).await {
+          return text_response(ctx, command, s).await;
         }
+        h
       }
       None => return text_response(ctx, command, "Not in a voice channel").await,
     };
 
     let handler = handler_lock.lock().await;
+    let outcome = Self::vote(
+      ctx,
+      guild_id,
+      command.member.as_deref(),
+      command.user.id,
+      &handler,
+    )
+    .await;
+    drop(handler);
 
-    if !handler.queue().is_empty() {
-      let current = match handler.queue().current() {
-        Some(t) => t,
-        None => return text_response(ctx, command, "Nothing to skip").await,
-      };
-
-      match handler.queue().skip() {
-        Err(e) => {
-          error!("Error skipping track: {}", e);
-          text_response(ctx, command, "Nothing to skip").await
-        }
-        Ok(_) => {
-          let metadata = SongMetadata::from_handle(&current).await;
-          let title = metadata.title.clone();
-
-          let length = format_duration_live(metadata.duration, &title);
-
-          match command
-            .edit_response(
-              &ctx.http,
-              EditInteractionResponse::new().embed(
-                CreateEmbed::new()
-                  .title("Skipped")
-                  .colour(EMBED_COLOUR)
-                  .fields(vec![
-                    ("Track", title, true),
-                    ("Length", length.to_string(), true),
-                  ]),
-              ),
-            )
-            .await
-          {
-            Ok(_m) => Ok(()),
-            Err(e) => Err(e),
-          }
-        }
+    let response = match outcome {
+      Err(s) => return text_response(ctx, command, s).await,
+      Ok(SkipOutcome::Skipped(current)) => EditInteractionResponse::new()
+        .embed(skipped_embed(&SongMetadata::from_handle(&current).await)),
+      Ok(SkipOutcome::Voted {
+        current,
+        votes,
+        required,
+      }) => {
+        let (embed, components) = vote_embed(&current, votes, required).await;
+        EditInteractionResponse::new()
+          .embed(embed)
+          .components(components)
       }
-    } else {
-      text_response(ctx, command, "Nothing to skip").await
+    };
+
+    match command.edit_response(&ctx.http, response).await {
+      Ok(_m) => Ok(()),
+      Err(e) => Err(e),
     }
   }
 
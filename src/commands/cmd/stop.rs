@@ -1,13 +1,24 @@
-use crate::commands::{playback::VOIPData, text_response, Command};
+use crate::commands::{panel, playback::VOIPData, text_response, Command};
+use crate::state::{modify_guild_state, LoopMode};
 use serenity::async_trait;
 use serenity::builder::CreateCommand;
 use serenity::client::Context;
 use serenity::model::application::CommandInteraction;
+use serenity::model::id::GuildId;
 use serenity::Error;
+use songbird::Call;
 use tracing::error;
 
 pub struct Stop;
 
+impl Stop {
+  pub async fn stop_playback(ctx: &Context, guild_id: GuildId, call: &Call) {
+    modify_guild_state(ctx, guild_id, |s| s.loop_mode = LoopMode::Off).await;
+    call.queue().stop();
+    panel::remove(ctx, guild_id).await;
+  }
+}
+
 #[async_trait]
 impl Command for Stop {
   async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
@@ -32,7 +43,7 @@ impl Command for Stop {
     };
 
     let handler = handler_lock.lock().await;
-    handler.queue().stop();
+    Self::stop_playback(ctx, guild_id, &handler).await;
 
     text_response(ctx, command, "Stopped playback and cleared the queue").await
   }
@@ -0,0 +1,160 @@
+use crate::commands::{
+  playback::{
+    format_volume, guild_settings, guild_volume, save_guild_settings, volume_scale,
+    SongMetadataKey, VOIPData,
+  },
+  text_response, Command,
+};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::Error;
+use tracing::error;
+
+pub struct Volume;
+
+const PERCENT_OPTION_NAME: &str = "percent";
+const TRACK_OPTION_NAME: &str = "track";
+
+const MAX_VOLUME: u16 = 200;
+
+#[async_trait]
+impl Command for Volume {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let options = command.data.options();
+    let percent = options.iter().find_map(|o| match o.value {
+      ResolvedValue::Integer(i) if o.name == PERCENT_OPTION_NAME => {
+        u16::try_from(i).ok().map(|v| v.min(MAX_VOLUME))
+      }
+      _ => None,
+    });
+    let track_only = options
+      .iter()
+      .find_map(|o| match o.value {
+        ResolvedValue::Boolean(b) if o.name == TRACK_OPTION_NAME => Some(b),
+        _ => None,
+      })
+      .unwrap_or(false);
+
+    let default_volume = guild_volume(ctx, guild_id).await;
+    let handler_lock = songbird::get(ctx).await.and_then(|m| m.get(guild_id));
+
+    let percent = match percent {
+      Some(p) => p,
+      None => {
+        let current = match &handler_lock {
+          Some(h) => h.lock().await.queue().current(),
+          None => None,
+        };
+        let track_volume = match current {
+          Some(handle) => {
+            let data = handle.typemap().read().await;
+            data.get::<SongMetadataKey>().and_then(|m| m.volume)
+          }
+          None => None,
+        };
+        return text_response(
+          ctx,
+          command,
+          format!("Volume: {}", format_volume(track_volume, default_volume)),
+        )
+        .await;
+      }
+    };
+
+    if let Some(h) = &handler_lock {
+      let voip_data = match VOIPData::from(ctx, command).await {
+        Ok(v) => v,
+        Err(s) => return text_response(ctx, command, s).await,
+      };
+      if !voip_data.compare_to_call(h).await {
+        return text_response(ctx, command, "You're not in the voice channel").await;
+      }
+    }
+
+    let queue = match &handler_lock {
+      Some(h) => h.lock().await.queue().current_queue(),
+      None => vec![],
+    };
+
+    if track_only {
+      let current = match queue.first() {
+        Some(c) => c,
+        None => return text_response(ctx, command, "Nothing playing").await,
+      };
+
+      {
+        let mut data = current.typemap().write().await;
+        if let Some(metadata) = data.get_mut::<SongMetadataKey>() {
+          metadata.volume = Some(percent);
+        }
+      }
+      if let Err(e) = current.set_volume(volume_scale(percent)) {
+        error!("Error setting track volume: {}", e);
+        return text_response(ctx, command, "Couldn't change volume").await;
+      }
+
+      return text_response(
+        ctx,
+        command,
+        format!("Volume set to {}% for this track", percent),
+      )
+      .await;
+    }
+
+    let mut settings = guild_settings(ctx, guild_id).await;
+    settings.volume = percent;
+    if let Err(e) = save_guild_settings(ctx, guild_id, settings).await {
+      error!("Error saving guild settings: {}", e);
+      return text_response(ctx, command, "Couldn't change volume").await;
+    }
+
+    // Tracks with their own volume keep it
+    for handle in &queue {
+      let overridden = {
+        let data = handle.typemap().read().await;
+        data
+          .get::<SongMetadataKey>()
+          .and_then(|m| m.volume)
+          .is_some()
+      };
+      if overridden {
+        continue;
+      }
+      if let Err(e) = handle.set_volume(volume_scale(percent)) {
+        error!("Error setting track volume: {}", e);
+      }
+    }
+
+    text_response(ctx, command, format!("Volume set to {}%", percent)).await
+  }
+
+  fn name() -> &'static str {
+    "volume"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Show or change the playback volume")
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::Integer,
+          PERCENT_OPTION_NAME,
+          "Volume in percent, from 0 to 200",
+        )
+        .min_int_value(0)
+        .max_int_value(MAX_VOLUME as u64),
+      )
+      .add_option(CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        TRACK_OPTION_NAME,
+        "Only change the current track instead of the server default",
+      ))
+  }
+}
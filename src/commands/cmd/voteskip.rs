@@ -0,0 +1,118 @@
+use crate::commands::{
+  playback::{guild_settings, save_guild_settings},
+  text_response, Command,
+};
+use serenity::async_trait;
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{
+  CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue,
+};
+use serenity::model::Permissions;
+use serenity::Error;
+use tracing::error;
+
+pub struct VoteSkip;
+
+const THRESHOLD_SUBCOMMAND: &str = "threshold";
+const DJ_ROLE_SUBCOMMAND: &str = "djrole";
+
+const PERCENT_OPTION_NAME: &str = "percent";
+const ROLE_OPTION_NAME: &str = "role";
+
+#[async_trait]
+impl Command for VoteSkip {
+  async fn execute(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let (subcommand, options) = match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+      }) => (name, options),
+      _ => {
+        error!("No subcommand provided");
+        return text_response(ctx, command, "Invalid subcommand").await;
+      }
+    };
+
+    let guild_id = match command.guild_id {
+      Some(g) => g,
+      None => return text_response(ctx, command, "Error getting guild information").await,
+    };
+
+    let mut settings = guild_settings(ctx, guild_id).await;
+    let text = match subcommand {
+      THRESHOLD_SUBCOMMAND => {
+        let percent = match options.iter().find_map(|o| match o.value {
+          ResolvedValue::Integer(i) if o.name == PERCENT_OPTION_NAME => u16::try_from(i).ok(),
+          _ => None,
+        }) {
+          Some(p) => p.min(100),
+          None => return text_response(ctx, command, "No threshold in request").await,
+        };
+        settings.skip_vote_percent = percent;
+        match percent {
+          0 => "Anyone in the channel can skip".to_string(),
+          p => format!("Skipping needs votes from {}% of listeners", p),
+        }
+      }
+      DJ_ROLE_SUBCOMMAND => {
+        let role = options.iter().find_map(|o| match o.value {
+          ResolvedValue::Role(role) if o.name == ROLE_OPTION_NAME => Some(role),
+          _ => None,
+        });
+        settings.dj_role_id = role.map(|r| r.id);
+        match role {
+          Some(r) => format!("Members with {} can skip without a vote", r.name),
+          None => "DJ role cleared".to_string(),
+        }
+      }
+      _ => return text_response(ctx, command, "Invalid subcommand").await,
+    };
+
+    if let Err(e) = save_guild_settings(ctx, guild_id, settings).await {
+      error!("Error saving guild settings: {}", e);
+      return text_response(ctx, command, "Couldn't change skip settings").await;
+    }
+    text_response(ctx, command, text).await
+  }
+
+  fn name() -> &'static str {
+    "voteskip"
+  }
+
+  fn info() -> CreateCommand {
+    CreateCommand::new(Self::name())
+      .description("Configure voting to skip tracks")
+      .default_member_permissions(Permissions::MANAGE_GUILD)
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          THRESHOLD_SUBCOMMAND,
+          "Set the share of listeners needed to skip",
+        )
+        .add_sub_option(
+          CreateCommandOption::new(
+            CommandOptionType::Integer,
+            PERCENT_OPTION_NAME,
+            "Percentage of listeners, 0 lets anyone skip instantly",
+          )
+          .min_int_value(0)
+          .max_int_value(100)
+          .required(true),
+        ),
+      )
+      .add_option(
+        CreateCommandOption::new(
+          CommandOptionType::SubCommand,
+          DJ_ROLE_SUBCOMMAND,
+          "Set the role that can skip without a vote",
+        )
+        .add_sub_option(CreateCommandOption::new(
+          CommandOptionType::Role,
+          ROLE_OPTION_NAME,
+          "DJ role, leave out to clear it",
+        )),
+      )
+  }
+}
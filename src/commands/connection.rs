@@ -0,0 +1,320 @@
+use crate::commands::playback::VOIPData;
+use crate::constants::EMBED_COLOUR;
+use crate::state::{modify_guild_state, read_guild_state, Recovery};
+use serenity::{
+  async_trait,
+  builder::{CreateEmbed, CreateMessage},
+  client::Context,
+  model::id::{ChannelId, GuildId},
+  model::{mention::Mentionable, voice::VoiceState},
+  prelude::Mutex,
+};
+use songbird::{
+  error::JoinResult,
+  events::context_data::{DisconnectKind, DisconnectReason},
+  model::CloseCode,
+  tracks::{PlayMode, TrackHandle},
+  Call, CoreEvent, Event, EventContext, EventHandler, Songbird,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+const RETRY_DELAYS: [Duration; 5] = [
+  Duration::from_secs(1),
+  Duration::from_secs(2),
+  Duration::from_secs(4),
+  Duration::from_secs(8),
+  Duration::from_secs(16),
+];
+
+// Every join goes through here so a new call gets its recovery handlers exactly once
+pub async fn join(
+  ctx: &Context,
+  manager: &Songbird,
+  voip_data: &VOIPData,
+) -> JoinResult<Arc<Mutex<Call>>> {
+  let guild_id = voip_data.guild_id;
+  let channel_id = voip_data.channel_id;
+  let new_call = manager.get(guild_id).is_none();
+
+  modify_guild_state(ctx, guild_id, |s| s.voice_channel = Some(channel_id)).await;
+  let result = manager.join(guild_id, channel_id).await;
+
+  if new_call {
+    if let Some(call) = manager.get(guild_id) {
+      let mut call = call.lock().await;
+      call.add_global_event(
+        Event::Core(CoreEvent::DriverDisconnect),
+        DriverDisconnected {
+          guild_id,
+          ctx: ctx.clone(),
+        },
+      );
+      call.add_global_event(
+        Event::Core(CoreEvent::DriverReconnect),
+        DriverReconnected {
+          guild_id,
+          ctx: ctx.clone(),
+        },
+      );
+    }
+  }
+
+  result
+}
+
+// Moves the whole call, queue included, keeping the current track's position across the switch
+pub async fn move_to(
+  ctx: &Context,
+  manager: &Songbird,
+  voip_data: &VOIPData,
+) -> JoinResult<Arc<Mutex<Call>>> {
+  let held = match manager.get(voip_data.guild_id) {
+    Some(call) => hold_current(&call).await,
+    None => None,
+  };
+  let result = join(ctx, manager, voip_data).await;
+  if let Some((track, position)) = held {
+    resume(&track, position).await;
+  }
+  result
+}
+
+// Notices the bot being dragged to another channel by someone, rather than moving itself
+pub async fn handle_voice_state(ctx: &Context, old: Option<&VoiceState>, new: &VoiceState) {
+  let (guild_id, channel_id) = match (new.guild_id, new.channel_id) {
+    (Some(g), Some(c)) => (g, c),
+    _ => return,
+  };
+  if new.user_id != ctx.cache.current_user().id {
+    return;
+  }
+  if old.and_then(|o| o.channel_id).is_none() {
+    return;
+  }
+
+  let (recorded, text_channel) = modify_guild_state(ctx, guild_id, |s| {
+    let recorded = s.voice_channel.replace(channel_id);
+    (
+      recorded,
+      s.text_channel
+        .or(s.now_playing.as_ref().map(|p| p.channel_id)),
+    )
+  })
+  .await;
+  if recorded == Some(channel_id) {
+    return;
+  }
+  info!("Moved to Channel({}) in Guild({})", channel_id, guild_id);
+
+  if let Err(e) = text_channel
+    .unwrap_or(channel_id)
+    .send_message(
+      &ctx.http,
+      CreateMessage::new().embed(
+        CreateEmbed::new()
+          .title("Moved to another voice channel")
+          .description(format!("Playing in {} from now on", channel_id.mention()))
+          .colour(EMBED_COLOUR),
+      ),
+    )
+    .await
+  {
+    error!("Error sending move notice: {}", e);
+  }
+}
+
+struct DriverDisconnected {
+  guild_id: GuildId,
+  ctx: Context,
+}
+
+#[async_trait]
+impl EventHandler for DriverDisconnected {
+  async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+    let data = match ctx {
+      EventContext::DriverDisconnect(d) => d,
+      _ => return None,
+    };
+    // Failed joins are reported by whoever asked to join
+    if data.kind == DisconnectKind::Connect {
+      return None;
+    }
+
+    let kicked = matches!(
+      data.reason,
+      Some(DisconnectReason::WsClosed(Some(CloseCode::Disconnected)))
+    );
+    match data.reason {
+      None | Some(DisconnectReason::Requested) => return None,
+      _ => warn!(
+        "Voice driver disconnected in Guild({}): {:?}",
+        self.guild_id, data.reason
+      ),
+    }
+
+    // Being moved also closes the connection, the new channel is already in the cache
+    let current = self
+      .guild_id
+      .to_guild_cached(&self.ctx.cache)
+      .and_then(|g| {
+        g.voice_states
+          .get(&self.ctx.cache.current_user().id)
+          .and_then(|vs| vs.channel_id)
+      });
+    if kicked && current.is_none() {
+      info!("Disconnected from voice in Guild({})", self.guild_id);
+      return None;
+    }
+
+    let recorded = read_guild_state(&self.ctx, self.guild_id, |s| s.voice_channel).await;
+    let channel_id = match current
+      .or(recorded)
+      .or(data.channel_id.map(|c| ChannelId::new(c.0.get())))
+    {
+      Some(c) => c,
+      None => {
+        error!("No voice channel to rejoin in Guild({})", self.guild_id);
+        return None;
+      }
+    };
+
+    start_recovery(&self.ctx, self.guild_id, channel_id).await;
+    None
+  }
+}
+
+struct DriverReconnected {
+  guild_id: GuildId,
+  ctx: Context,
+}
+
+#[async_trait]
+impl EventHandler for DriverReconnected {
+  async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+    info!("Voice driver reconnected in Guild({})", self.guild_id);
+
+    // The driver got there on its own before the backoff did
+    if let Some(recovery) =
+      modify_guild_state(&self.ctx, self.guild_id, |s| s.recovery.take()).await
+    {
+      recovery.task.abort();
+      if let Some((track, position)) = recovery.resume {
+        resume(&track, position).await;
+      }
+    }
+    None
+  }
+}
+
+async fn start_recovery(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) {
+  let manager = match songbird::get(ctx).await {
+    Some(arc) => arc.clone(),
+    None => {
+      error!("Error with songbird client");
+      return;
+    }
+  };
+  // Hold the track where the connection dropped instead of letting it play to nobody
+  let resume = match manager.get(guild_id) {
+    Some(call) => hold_current(&call).await,
+    None => return,
+  };
+
+  let recovery_ctx = ctx.clone();
+  modify_guild_state(ctx, guild_id, move |s| {
+    if let Some(previous) = s.recovery.take() {
+      previous.task.abort();
+    }
+    s.recovery = Some(Recovery {
+      resume,
+      task: tokio::spawn(recover(recovery_ctx, manager, guild_id, channel_id)),
+    });
+  })
+  .await;
+}
+
+async fn recover(ctx: Context, manager: Arc<Songbird>, guild_id: GuildId, channel_id: ChannelId) {
+  let voip_data = VOIPData {
+    channel_id,
+    guild_id,
+  };
+
+  for (attempt, delay) in RETRY_DELAYS.iter().enumerate() {
+    tokio::time::sleep(*delay).await;
+
+    // Left on purpose in the meantime
+    if manager.get(guild_id).is_none() {
+      modify_guild_state(&ctx, guild_id, |s| s.recovery = None).await;
+      return;
+    }
+
+    match join(&ctx, &manager, &voip_data).await {
+      Ok(_) => {
+        info!(
+          "Rejoined voice in Guild({}) after {} attempts",
+          guild_id,
+          attempt + 1
+        );
+        let recovery = modify_guild_state(&ctx, guild_id, |s| s.recovery.take()).await;
+        if let Some((track, position)) = recovery.and_then(|r| r.resume) {
+          resume(&track, position).await;
+        }
+        return;
+      }
+      Err(e) => warn!(
+        "Error rejoining voice in Guild({}), attempt {}: {}",
+        guild_id,
+        attempt + 1,
+        e
+      ),
+    }
+  }
+
+  error!("Gave up rejoining voice in Guild({})", guild_id);
+  let text_channel = modify_guild_state(&ctx, guild_id, |s| {
+    s.recovery = None;
+    s.text_channel
+      .or(s.now_playing.as_ref().map(|p| p.channel_id))
+  })
+  .await;
+  if let Err(e) = text_channel
+    .unwrap_or(channel_id)
+    .send_message(
+      &ctx.http,
+      CreateMessage::new().embed(
+        CreateEmbed::new()
+          .title("Lost connection to the voice channel")
+          .description(format!(
+            "Couldn't reconnect after {} attempts, use /join to try again",
+            RETRY_DELAYS.len()
+          ))
+          .colour(EMBED_COLOUR),
+      ),
+    )
+    .await
+  {
+    error!("Error sending reconnect notice: {}", e);
+  }
+}
+
+async fn hold_current(call: &Arc<Mutex<Call>>) -> Option<(TrackHandle, Duration)> {
+  let track = call.lock().await.queue().current()?;
+  let state = track.get_info().await.ok()?;
+  if !matches!(state.playing, PlayMode::Play) {
+    return None;
+  }
+  if let Err(e) = track.pause() {
+    error!("Error pausing track: {}", e);
+  }
+  Some((track, state.position))
+}
+
+async fn resume(track: &TrackHandle, position: Duration) {
+  if let Err(e) = track.seek(position).result_async().await {
+    error!("Error seeking back to the held position: {}", e);
+  }
+  if let Err(e) = track.play() {
+    error!("Error resuming held track: {}", e);
+  }
+}
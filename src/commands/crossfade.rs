@@ -0,0 +1,117 @@
+use crate::commands::playback::{
+  format_duration_live, guild_settings, output_length, volume_scale, SongMetadata,
+};
+use crate::state::{read_guild_state, LoopMode};
+use serenity::{client::Context, model::id::GuildId};
+use songbird::tracks::{PlayMode, TrackHandle};
+use std::{f32::consts::FRAC_PI_2, time::Duration};
+use tracing::error;
+
+pub const MAX_CROSSFADE_SECS: u16 = 12;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const FADE_STEP: Duration = Duration::from_millis(100);
+
+// Runs for as long as the track plays, starting the next track early once it's within the crossfade window
+pub async fn watch(ctx: Context, guild_id: GuildId, current: TrackHandle) {
+  let metadata = SongMetadata::from_handle(&current).await;
+  let live = bool::from(&format_duration_live(metadata.duration, &metadata.title));
+  if live || metadata.duration.is_zero() {
+    return;
+  }
+
+  loop {
+    tokio::time::sleep(POLL_INTERVAL).await;
+
+    let state = match current.get_info().await {
+      Ok(s) => s,
+      Err(_) => return,
+    };
+    if state.playing.is_done() {
+      return;
+    }
+    if !matches!(state.playing, PlayMode::Play) {
+      // Paused to make way for another track, it gets a new watcher when it starts again
+      let front = match songbird::get(&ctx).await.and_then(|m| m.get(guild_id)) {
+        Some(h) => h.lock().await.queue().current(),
+        None => return,
+      };
+      match front.is_some_and(|t| t.uuid() == current.uuid()) {
+        true => continue,
+        false => return,
+      }
+    }
+
+    let crossfade = Duration::from_secs(guild_settings(&ctx, guild_id).await.crossfade_secs.into());
+    let remaining = output_length(&ctx, guild_id, metadata.duration)
+      .await
+      .saturating_sub(state.position);
+    if crossfade.is_zero() || remaining > crossfade {
+      continue;
+    }
+    if read_guild_state(&ctx, guild_id, |s| s.loop_mode).await == LoopMode::Track {
+      continue;
+    }
+
+    let next = match songbird::get(&ctx).await.and_then(|m| m.get(guild_id)) {
+      Some(h) => h.lock().await.queue().current_queue().get(1).cloned(),
+      None => return,
+    };
+    if let Some(next) = next {
+      fade(&ctx, guild_id, &current, &next, remaining).await;
+    }
+    return;
+  }
+}
+
+async fn fade(
+  ctx: &Context,
+  guild_id: GuildId,
+  current: &TrackHandle,
+  next: &TrackHandle,
+  length: Duration,
+) {
+  let default_volume = guild_settings(ctx, guild_id).await.volume;
+  let current_volume = volume_scale(
+    SongMetadata::from_handle(current)
+      .await
+      .volume
+      .unwrap_or(default_volume),
+  );
+  let next_volume = volume_scale(
+    SongMetadata::from_handle(next)
+      .await
+      .volume
+      .unwrap_or(default_volume),
+  );
+
+  if let Err(e) = next.set_volume(0.0).and_then(|_| next.play()) {
+    error!("Error starting crossfade: {}", e);
+    return;
+  }
+
+  let steps = (length.as_millis() / FADE_STEP.as_millis()).max(1) as u32;
+  for step in 1..=steps {
+    tokio::time::sleep(FADE_STEP).await;
+
+    // Equal power curve so the overall loudness doesn't dip halfway through
+    let progress = step as f32 / steps as f32;
+    let out = (progress * FRAC_PI_2).cos();
+    let into = (progress * FRAC_PI_2).sin();
+    if current.set_volume(current_volume * out).is_err() {
+      break;
+    }
+    if let Err(e) = next.set_volume(next_volume * into) {
+      error!("Error fading in next track: {}", e);
+      return;
+    }
+  }
+
+  if let Err(e) = next.set_volume(next_volume) {
+    error!("Error restoring track volume: {}", e);
+  }
+  // Ending the faded out track lets the queue move on to the one that's already playing
+  if let Err(e) = current.stop() {
+    error!("Error stopping faded out track: {}", e);
+  }
+}
@@ -0,0 +1,282 @@
+use crate::commands::playback::{SongMetadata, SourceKind};
+use crate::constants::{placeholder_img, HttpClient};
+use reqwest::{header::CONTENT_TYPE, Url};
+use serenity::model::channel::Attachment;
+use songbird::input::{codecs::get_probe, Compose, HttpRequest};
+use std::time::Duration;
+use symphonia::core::{
+  codecs::CODEC_TYPE_NULL,
+  errors::Error as SymphoniaError,
+  formats::{FormatOptions, FormatReader},
+  io::{MediaSource, MediaSourceStream, ReadBytes},
+  meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+  probe::Hint,
+  units::TimeBase,
+};
+use tracing::error;
+
+const MEDIA_EXTENSIONS: [&str; 10] = [
+  "mp3", "flac", "ogg", "oga", "opus", "wav", "m4a", "aac", "mka", "weba",
+];
+const CONTENT_TYPE_TIMEOUT: Duration = Duration::from_secs(5);
+const ESTIMATE_PACKETS: usize = 32;
+// Attachment and upload links are signed and stop working after about a day
+const EXPIRING_HOSTS: [&str; 2] = ["cdn.discordapp.com", "media.discordapp.net"];
+// Sites yt-dlp extracts from, whose pages are never the audio file itself
+const YTDLP_HOSTS: [&str; 8] = [
+  "youtube.com",
+  "youtu.be",
+  "soundcloud.com",
+  "bandcamp.com",
+  "vimeo.com",
+  "twitch.tv",
+  "mixcloud.com",
+  "dailymotion.com",
+];
+
+pub struct CoverArt {
+  pub data: Vec<u8>,
+  pub media_type: String,
+}
+
+impl CoverArt {
+  pub fn file_name(&self) -> String {
+    let extension = match self.media_type.as_str() {
+      "image/png" => "png",
+      "image/gif" => "gif",
+      "image/webp" => "webp",
+      _ => "jpg",
+    };
+    format!("cover.{}", extension)
+  }
+}
+
+#[derive(Default)]
+struct Tags {
+  title: Option<String>,
+  artist: Option<String>,
+  duration: Option<Duration>,
+  cover: Option<CoverArt>,
+}
+
+impl Tags {
+  fn read(&mut self, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+      match tag.std_key {
+        Some(StandardTagKey::TrackTitle) => self.title = Some(tag.value.to_string()),
+        Some(StandardTagKey::Artist) => self.artist = Some(tag.value.to_string()),
+        _ => (),
+      }
+    }
+    if let Some(visual) = revision.visuals().first() {
+      self.cover = Some(CoverArt {
+        data: visual.data.to_vec(),
+        media_type: visual.media_type.clone(),
+      });
+    }
+  }
+}
+
+// Links straight to an audio file can be streamed without going through yt-dlp
+pub async fn is_direct_media(client: &HttpClient, url: &str) -> bool {
+  let url = match Url::parse(url) {
+    Ok(u) => u,
+    Err(_) => return false,
+  };
+  if extension(&url).is_some_and(|e| MEDIA_EXTENSIONS.contains(&e.as_str())) {
+    return true;
+  }
+  let ytdlp_host = url.host_str().is_some_and(|host| {
+    YTDLP_HOSTS
+      .iter()
+      .any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+  });
+  if ytdlp_host {
+    return false;
+  }
+
+  match client.head(url).timeout(CONTENT_TYPE_TIMEOUT).send().await {
+    Ok(response) => response
+      .headers()
+      .get(CONTENT_TYPE)
+      .and_then(|t| t.to_str().ok())
+      .is_some_and(|t| t.starts_with("audio/") || t.starts_with("application/ogg")),
+    Err(_) => false,
+  }
+}
+
+pub fn is_expiring(url: &str) -> bool {
+  Url::parse(url)
+    .ok()
+    .and_then(|u| u.host_str().map(|h| EXPIRING_HOSTS.contains(&h)))
+    .unwrap_or(false)
+}
+
+pub fn is_audio_attachment(attachment: &Attachment) -> bool {
+  match &attachment.content_type {
+    Some(t) => t.starts_with("audio/") || t.starts_with("application/ogg"),
+    None => attachment
+      .filename
+      .rsplit_once('.')
+      .is_some_and(|(_, e)| MEDIA_EXTENSIONS.contains(&e.to_lowercase().as_str())),
+  }
+}
+
+pub async fn probe(client: HttpClient, url: String) -> (SongMetadata, Option<CoverArt>) {
+  let file_name = Url::parse(&url)
+    .ok()
+    .as_ref()
+    .and_then(|u| u.path_segments())
+    .and_then(|mut s| s.next_back())
+    .filter(|s| !s.is_empty())
+    .map(|s| s.to_string());
+  probe_named(client, url, file_name).await
+}
+
+pub async fn probe_attachment(
+  client: HttpClient,
+  attachment: &Attachment,
+) -> (SongMetadata, Option<CoverArt>) {
+  let (metadata, cover) = probe_named(
+    client,
+    attachment.url.clone(),
+    Some(attachment.filename.clone()),
+  )
+  .await;
+  let metadata = SongMetadata {
+    source_kind: SourceKind::Attachment,
+    ..metadata
+  };
+  (metadata, cover)
+}
+
+// Reads tags, duration and cover art from the start of the file, falling back to its name
+async fn probe_named(
+  client: HttpClient,
+  url: String,
+  file_name: Option<String>,
+) -> (SongMetadata, Option<CoverArt>) {
+  let parsed = Url::parse(&url).ok();
+  let tags = match HttpRequest::new(client, url.clone()).create_async().await {
+    Ok(stream) => {
+      // The hint already has the Content-Type, the extension helps when that's generic
+      let mut hint = stream.hint.unwrap_or_default();
+      if let Some(extension) = parsed.as_ref().and_then(extension) {
+        hint.with_extension(&extension);
+      }
+      let input = stream.input;
+      match tokio::task::spawn_blocking(move || read_tags(input, hint)).await {
+        Ok(Ok(t)) => t,
+        Ok(Err(e)) => {
+          error!("Error reading tags from {}: {}", url, e);
+          Tags::default()
+        }
+        Err(e) => {
+          error!("Error reading tags from {}: {}", url, e);
+          Tags::default()
+        }
+      }
+    }
+    Err(e) => {
+      error!("Error opening {}: {}", url, e);
+      Tags::default()
+    }
+  };
+
+  let title = match (tags.artist, tags.title) {
+    (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+    (None, Some(title)) => title,
+    _ => file_name.unwrap_or_else(|| url.clone()),
+  };
+
+  let metadata = SongMetadata {
+    title,
+    thumbnail: placeholder_img(),
+    duration: tags.duration.unwrap_or_default(),
+    url: Some(url),
+    requester: None,
+    source_kind: SourceKind::Direct,
+    volume: None,
+  };
+  (metadata, tags.cover)
+}
+
+fn read_tags(input: Box<dyn MediaSource>, hint: Hint) -> Result<Tags, SymphoniaError> {
+  let byte_len = input.byte_len();
+  let source = MediaSourceStream::new(input, Default::default());
+  let mut probed = get_probe().format(
+    &hint,
+    source,
+    &FormatOptions::default(),
+    &MetadataOptions::default(),
+  )?;
+
+  let track = probed
+    .format
+    .tracks()
+    .iter()
+    .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+    .map(|t| (t.id, t.codec_params.time_base, t.codec_params.n_frames));
+  let mut tags = Tags {
+    duration: track.and_then(|(_, time_base, n_frames)| {
+      let time = time_base?.calc_time(n_frames?);
+      Duration::try_from_secs_f64(time.seconds as f64 + time.frac).ok()
+    }),
+    ..Default::default()
+  };
+
+  // ID3v2 tags sit before the container, the container can have its own as well
+  if let Some(metadata) = probed.metadata.get() {
+    if let Some(revision) = metadata.current() {
+      tags.read(revision);
+    }
+  }
+  if let Some(revision) = probed.format.metadata().current() {
+    tags.read(revision);
+  }
+
+  if tags.duration.is_none() {
+    if let (Some((track_id, Some(time_base), _)), Some(byte_len)) = (track, byte_len) {
+      tags.duration = estimate_duration(probed.format, track_id, time_base, byte_len);
+    }
+  }
+
+  Ok(tags)
+}
+
+// MP3s without a Xing or VBRI header don't say how many frames they have,
+// so the bitrate of the first few packets and the file size stand in for it
+fn estimate_duration(
+  mut format: Box<dyn FormatReader>,
+  track_id: u32,
+  time_base: TimeBase,
+  byte_len: u64,
+) -> Option<Duration> {
+  let mut bytes = 0;
+  let mut frames = 0;
+  for _ in 0..ESTIMATE_PACKETS {
+    let packet = match format.next_packet() {
+      Ok(p) => p,
+      Err(_) => break,
+    };
+    if packet.track_id() == track_id {
+      bytes += packet.data.len() as u64;
+      frames += packet.dur;
+    }
+  }
+  if bytes == 0 || frames == 0 {
+    return None;
+  }
+
+  // Whatever came before the packets just read is tags and headers
+  let audio_start = format.into_inner().pos().saturating_sub(bytes);
+  let time = time_base.calc_time(frames);
+  let seconds_per_byte = (time.seconds as f64 + time.frac) / bytes as f64;
+  Duration::try_from_secs_f64(byte_len.saturating_sub(audio_start) as f64 * seconds_per_byte).ok()
+}
+
+fn extension(url: &Url) -> Option<String> {
+  let name = url.path_segments()?.next_back()?;
+  let (_, extension) = name.rsplit_once('.')?;
+  Some(extension.to_lowercase())
+}
@@ -0,0 +1,142 @@
+use crate::commands::{
+  crossfade, panel,
+  playback::{enqueue_track, SongMetadata, TrackRemovedKey},
+};
+use crate::state::{modify_guild_state, read_guild_state, LoopMode};
+use crate::storage::{database, NewHistoryEntry};
+use serenity::{
+  async_trait,
+  client::Context,
+  model::id::{ChannelId, GuildId},
+};
+use songbird::{
+  events::Event,
+  tracks::{PlayMode, TrackHandle},
+  EventContext, EventHandler,
+};
+use tracing::{error, info};
+
+pub struct SongStart {
+  pub channel_id: ChannelId,
+  pub guild_id: GuildId,
+  pub ctx: Context,
+}
+
+#[async_trait]
+impl EventHandler for SongStart {
+  async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+    if let EventContext::Track(track_ctx) = ctx {
+      track_started(&self.ctx, self.guild_id, self.channel_id, track_ctx[0].1).await;
+    }
+    Some(Event::Cancel)
+  }
+}
+
+pub async fn track_started(
+  ctx: &Context,
+  guild_id: GuildId,
+  channel_id: ChannelId,
+  handle: &TrackHandle,
+) {
+  tokio::spawn(crossfade::watch(ctx.clone(), guild_id, handle.clone()));
+  modify_guild_state(ctx, guild_id, |s| s.skip_vote = None).await;
+  panel::show(ctx, guild_id, channel_id).await;
+}
+
+pub struct SongEnd {
+  pub channel_id: ChannelId,
+  pub guild_id: GuildId,
+  pub ctx: Context,
+}
+
+#[async_trait]
+impl EventHandler for SongEnd {
+  async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+    let (state, handle) = if let EventContext::Track(track_ctx) = ctx {
+      track_ctx[0]
+    } else {
+      return Some(Event::Cancel);
+    };
+
+    if let PlayMode::Errored(_) = state.playing {
+      return None;
+    }
+
+    if handle
+      .typemap()
+      .read()
+      .await
+      .contains_key::<TrackRemovedKey>()
+    {
+      return None;
+    }
+
+    let metadata = SongMetadata::from_handle(handle).await;
+
+    // Stopping the queue ends every queued track, only keep the ones that actually played
+    if let (Some(url), false) = (metadata.saved_url(), state.play_time.is_zero()) {
+      let entry = NewHistoryEntry {
+        title: metadata.title.clone(),
+        url,
+        duration: metadata.duration,
+        thumbnail: metadata.saved_thumbnail(),
+        requested_by: metadata.requester.as_ref().map(|r| r.display_name.clone()),
+        source_kind: Some(metadata.source_kind.name().to_string()),
+      };
+      if let Err(e) = database(&self.ctx)
+        .await
+        .record_play(self.guild_id, entry)
+        .await
+      {
+        error!("Error recording play history: {}", e);
+      }
+    }
+
+    let loop_mode = read_guild_state(&self.ctx, self.guild_id, |s| s.loop_mode).await;
+    if loop_mode != LoopMode::Queue {
+      return None;
+    }
+
+    let http_client = {
+      let data = self.ctx.data.read().await;
+      data
+        .get::<crate::constants::HttpKey>()
+        .cloned()
+        .expect("HttpClient did not exist")
+    };
+    let source = match metadata.source(http_client) {
+      Some(s) => s,
+      None => {
+        error!("No URL to requeue {} from", metadata.title);
+        return None;
+      }
+    };
+
+    let manager = match songbird::get(&self.ctx).await {
+      Some(arc) => arc.clone(),
+      None => {
+        error!("Error with songbird client");
+        return None;
+      }
+    };
+
+    let handler_lock = match manager.get(self.guild_id) {
+      Some(h) => h,
+      None => return None,
+    };
+
+    let mut handler = handler_lock.lock().await;
+    enqueue_track(
+      &self.ctx,
+      &mut handler,
+      source,
+      metadata,
+      self.channel_id,
+      self.guild_id,
+    )
+    .await;
+
+    info!("Requeued track in Guild({})", self.guild_id);
+    None
+  }
+}
@@ -1,19 +1,33 @@
 use crate::config::ConfigStorage;
 use crate::constants::EMBED_COLOUR;
+use crate::state::modify_guild_state;
 use serenity::builder::CreateEmbed;
-use serenity::builder::CreateInteractionResponseMessage;
 use serenity::builder::EditInteractionResponse;
-use serenity::model::application::CommandInteraction;
+use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::model::application::{CommandInteraction, ComponentInteraction};
 use serenity::model::prelude::Ready;
+use serenity::model::voice::VoiceState;
 use serenity::prelude::Context;
 use serenity::Error;
 use serenity::{async_trait, builder::CreateCommand};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+mod auto_leave;
+mod autocomplete;
 mod cmd;
+mod connection;
+mod crossfade;
+mod direct;
+mod events;
+mod panel;
+mod permissions;
 mod playback;
+mod snapshot;
 mod utils;
 
+pub use autocomplete::AutocompleteStorage;
+pub use snapshot::{restore_queues, save_queues, save_queues_periodically};
+
 static COMMAND_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(10);
 
 #[async_trait]
@@ -79,6 +93,19 @@ fn command_list() -> Vec<CreateCommand> {
     cmd::Pause::info(),
     cmd::Resume::info(),
     cmd::Status::info(),
+    cmd::Loop::info(),
+    cmd::Search::info(),
+    cmd::Playlist::info(),
+    cmd::History::info(),
+    cmd::Previous::info(),
+    cmd::Volume::info(),
+    cmd::Filter::info(),
+    cmd::Normalize::info(),
+    cmd::Crossfade::info(),
+    cmd::VoteSkip::info(),
+    cmd::Permissions::info(),
+    cmd::ChannelPolicy::info(),
+    cmd::PlayThis::info(),
   ]
 }
 
@@ -88,9 +115,7 @@ pub async fn handle_commands(ctx: &Context, command: CommandInteraction) {
   match command
     .create_response(
       &ctx.http,
-      serenity::builder::CreateInteractionResponse::Defer(
-        CreateInteractionResponseMessage::new().content("Loading"),
-      ),
+      CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().content("Loading")),
     )
     .await
   {
@@ -98,6 +123,34 @@ pub async fn handle_commands(ctx: &Context, command: CommandInteraction) {
     Err(e) => error!("Error deferring command {}: {}", name, e),
   }
 
+  if let Some(guild_id) = command.guild_id {
+    let channel_id = command.channel_id;
+    modify_guild_state(ctx, guild_id, |s| s.text_channel = Some(channel_id)).await;
+  }
+
+  if let Err(reason) = permissions::check(ctx, &command).await {
+    info!(
+      "{user} was denied command {cmd}",
+      user = user.tag(),
+      cmd = name
+    );
+    if let Err(e) = command
+      .edit_response(
+        &ctx.http,
+        EditInteractionResponse::new().embed(
+          CreateEmbed::new()
+            .title("Permission denied")
+            .description(reason)
+            .colour(EMBED_COLOUR),
+        ),
+      )
+      .await
+    {
+      error!("Couldn't respond to command: {}", e);
+    }
+    return;
+  }
+
   let result = match name.as_str() {
     _ if name == cmd::Join::name() => cmd::Join::execute(ctx, &command),
     _ if name == cmd::Leave::name() => cmd::Leave::execute(ctx, &command),
@@ -113,6 +166,19 @@ pub async fn handle_commands(ctx: &Context, command: CommandInteraction) {
     _ if name == cmd::Pause::name() => cmd::Pause::execute(ctx, &command),
     _ if name == cmd::Resume::name() => cmd::Resume::execute(ctx, &command),
     _ if name == cmd::Status::name() => cmd::Status::execute(ctx, &command),
+    _ if name == cmd::Loop::name() => cmd::Loop::execute(ctx, &command),
+    _ if name == cmd::Search::name() => cmd::Search::execute(ctx, &command),
+    _ if name == cmd::Playlist::name() => cmd::Playlist::execute(ctx, &command),
+    _ if name == cmd::History::name() => cmd::History::execute(ctx, &command),
+    _ if name == cmd::Previous::name() => cmd::Previous::execute(ctx, &command),
+    _ if name == cmd::Volume::name() => cmd::Volume::execute(ctx, &command),
+    _ if name == cmd::Filter::name() => cmd::Filter::execute(ctx, &command),
+    _ if name == cmd::Normalize::name() => cmd::Normalize::execute(ctx, &command),
+    _ if name == cmd::Crossfade::name() => cmd::Crossfade::execute(ctx, &command),
+    _ if name == cmd::VoteSkip::name() => cmd::VoteSkip::execute(ctx, &command),
+    _ if name == cmd::Permissions::name() => cmd::Permissions::execute(ctx, &command),
+    _ if name == cmd::ChannelPolicy::name() => cmd::ChannelPolicy::execute(ctx, &command),
+    _ if name == cmd::PlayThis::name() => cmd::PlayThis::execute(ctx, &command),
     _ => Box::pin(text_response(ctx, &command, "Invalid command")),
   };
 
@@ -146,6 +212,84 @@ pub async fn handle_commands(ctx: &Context, command: CommandInteraction) {
   }
 }
 
+pub async fn handle_voice_state(ctx: &Context, old: Option<VoiceState>, new: VoiceState) {
+  connection::handle_voice_state(ctx, old.as_ref(), &new).await;
+  auto_leave::handle_voice_state(ctx, old, new).await;
+}
+
+pub async fn handle_components(ctx: &Context, component: ComponentInteraction) {
+  let custom_id = component.data.custom_id.as_str();
+
+  // Queue pages and search menus are answered by the collector attached to the message
+  if custom_id.starts_with(cmd::Queue::BUTTON_ID_PREFIX)
+    || custom_id.starts_with(cmd::Search::COMPONENT_ID_PREFIX)
+  {
+    return;
+  }
+
+  let result = match custom_id {
+    _ if custom_id.starts_with(panel::BUTTON_ID_PREFIX) => {
+      panel::handle_button(ctx, &component).await
+    }
+    _ if custom_id.starts_with(cmd::Skip::BUTTON_ID_PREFIX) => {
+      cmd::Skip::handle_vote_button(ctx, &component).await
+    }
+    _ => {
+      warn!(
+        "{user} used unknown component {id}",
+        user = component.user.tag(),
+        id = custom_id
+      );
+      return;
+    }
+  };
+
+  match result {
+    Ok(_) => info!(
+      "{user} used component {id}",
+      user = component.user.tag(),
+      id = custom_id
+    ),
+    Err(e) => error!("Couldn't respond to component {}: {}", custom_id, e),
+  }
+}
+
+pub async fn handle_autocomplete(ctx: &Context, interaction: CommandInteraction) {
+  let name = interaction.data.name.as_str();
+
+  let result = match name {
+    _ if name == cmd::Play::name() => autocomplete::play_suggestions(ctx, &interaction),
+    _ => {
+      warn!("No autocomplete for command {}", name);
+      return;
+    }
+  };
+
+  if let Err(e) = result.await {
+    error!("Couldn't respond to autocomplete for {}: {}", name, e);
+  }
+}
+
+pub async fn component_text_response<D>(
+  ctx: &Context,
+  component: &ComponentInteraction,
+  text: D,
+) -> Result<(), Error>
+where
+  std::string::String: From<D>,
+{
+  component
+    .create_response(
+      &ctx.http,
+      CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+          .embed(CreateEmbed::new().title(text).colour(EMBED_COLOUR))
+          .ephemeral(true),
+      ),
+    )
+    .await
+}
+
 pub async fn text_response<D>(
   ctx: &Context,
   command: &CommandInteraction,
@@ -0,0 +1,334 @@
+use crate::commands::{
+  cmd::{Loop, Pause, Resume, Skip, SkipOutcome, Stop},
+  component_text_response, permissions,
+  playback::{
+    format_duration, format_duration_live, format_volume, get_queue_length_and_duration,
+    guild_volume, output_length, queue_footer, SongMetadata, VOIPData,
+  },
+  utils::remove_md_characters,
+};
+use crate::constants::EMBED_COLOUR;
+use crate::state::{modify_guild_state, read_guild_state, NowPlaying};
+use serenity::{
+  builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, EditMessage,
+  },
+  client::Context,
+  model::application::{ButtonStyle, ComponentInteraction},
+  model::id::{ChannelId, GuildId, MessageId},
+  Error,
+};
+use songbird::tracks::{PlayMode, TrackHandle};
+use std::time::Duration;
+use tracing::error;
+
+pub const BUTTON_ID_PREFIX: &str = "np_";
+const PAUSE_BUTTON_ID: &str = "np_pause";
+const RESUME_BUTTON_ID: &str = "np_resume";
+const SKIP_BUTTON_ID: &str = "np_skip";
+const STOP_BUTTON_ID: &str = "np_stop";
+const LOOP_BUTTON_ID: &str = "np_loop";
+
+const UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+const PROGRESS_BAR_LENGTH: usize = 20;
+
+pub async fn show(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) {
+  let current = match current_track(ctx, guild_id).await {
+    Some(c) => c,
+    None => return,
+  };
+
+  let (embed, components) = render(ctx, guild_id, &current).await;
+  let message = match channel_id
+    .send_message(
+      &ctx.http,
+      CreateMessage::new().embed(embed).components(components),
+    )
+    .await
+  {
+    Ok(m) => m,
+    Err(e) => {
+      error!("Error sending now playing message: {}", e);
+      return;
+    }
+  };
+
+  let updater = tokio::spawn(update(ctx.clone(), guild_id, channel_id, message.id));
+  let previous = modify_guild_state(ctx, guild_id, |s| {
+    s.now_playing.replace(NowPlaying {
+      channel_id,
+      message_id: message.id,
+      updater,
+    })
+  })
+  .await;
+
+  if let Some(previous) = previous {
+    delete(ctx, previous).await;
+  }
+}
+
+pub async fn remove(ctx: &Context, guild_id: GuildId) {
+  if let Some(panel) = modify_guild_state(ctx, guild_id, |s| s.now_playing.take()).await {
+    delete(ctx, panel).await;
+  }
+}
+
+async fn delete(ctx: &Context, panel: NowPlaying) {
+  panel.updater.abort();
+  if let Err(e) = panel
+    .channel_id
+    .delete_message(&ctx.http, panel.message_id)
+    .await
+  {
+    error!("Error deleting now playing message: {}", e);
+  }
+}
+
+async fn update(ctx: Context, guild_id: GuildId, channel_id: ChannelId, message_id: MessageId) {
+  loop {
+    tokio::time::sleep(UPDATE_INTERVAL).await;
+
+    let current = match current_track(&ctx, guild_id).await {
+      Some(c) => c,
+      None => {
+        let owned = modify_guild_state(&ctx, guild_id, |s| {
+          match s.now_playing.as_ref().map(|p| p.message_id) {
+            Some(id) if id == message_id => s.now_playing.take(),
+            _ => None,
+          }
+        })
+        .await;
+
+        if owned.is_some() {
+          if let Err(e) = channel_id.delete_message(&ctx.http, message_id).await {
+            error!("Error deleting now playing message: {}", e);
+          }
+        }
+        return;
+      }
+    };
+
+    let (embed, components) = render(&ctx, guild_id, &current).await;
+    if let Err(e) = channel_id
+      .edit_message(
+        &ctx.http,
+        message_id,
+        EditMessage::new().embed(embed).components(components),
+      )
+      .await
+    {
+      error!("Error updating now playing message: {}", e);
+      return;
+    }
+  }
+}
+
+async fn current_track(ctx: &Context, guild_id: GuildId) -> Option<TrackHandle> {
+  let manager = songbird::get(ctx).await?;
+  let handler_lock = manager.get(guild_id)?;
+  let handler = handler_lock.lock().await;
+  handler.queue().current()
+}
+
+async fn render(
+  ctx: &Context,
+  guild_id: GuildId,
+  current: &TrackHandle,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+  let metadata = SongMetadata::from_handle(current).await;
+  let (position, paused) = match current.get_info().await {
+    Ok(state) => (state.position, matches!(state.playing, PlayMode::Pause)),
+    Err(e) => {
+      error!("Couldn't get track state: {}", e);
+      (Duration::default(), false)
+    }
+  };
+
+  let queue = match songbird::get(ctx).await.and_then(|m| m.get(guild_id)) {
+    Some(h) => h.lock().await.queue().current_queue(),
+    None => vec![],
+  };
+  let (count, duration) = get_queue_length_and_duration(&queue).await;
+  let duration = output_length(ctx, guild_id, duration).await;
+  let loop_mode = read_guild_state(ctx, guild_id, |s| s.loop_mode).await;
+  let volume = format_volume(metadata.volume, guild_volume(ctx, guild_id).await);
+
+  // The position counts output time, which a speed preset stretches or squeezes
+  let track_length = output_length(ctx, guild_id, metadata.duration).await;
+  let length = format_duration_live(track_length, &metadata.title);
+  let progress = match bool::from(&length) {
+    true => format!("{} / LIVE", format_duration(position)),
+    false => format!(
+      "`{}`\n{} / {}",
+      progress_bar(position, track_length),
+      format_duration(position),
+      length
+    ),
+  };
+
+  let mut fields = vec![
+    ("Track", remove_md_characters(metadata.title.clone()), false),
+    ("Progress", progress, false),
+    ("Volume", volume, false),
+  ];
+  if let Some(requested_by) = metadata.requested_by() {
+    fields.push(("Requested by", requested_by, false));
+  }
+
+  let embed = CreateEmbed::new()
+    .title(if paused { "Paused" } else { "Now Playing" })
+    .colour(EMBED_COLOUR)
+    .thumbnail(metadata.thumbnail.clone())
+    .fields(fields)
+    .footer(queue_footer(count, format_duration(duration), loop_mode));
+
+  let mut controls = vec![
+    match paused {
+      true => CreateButton::new(RESUME_BUTTON_ID)
+        .label("Resume")
+        .style(ButtonStyle::Success),
+      false => CreateButton::new(PAUSE_BUTTON_ID)
+        .label("Pause")
+        .style(ButtonStyle::Secondary),
+    },
+    CreateButton::new(SKIP_BUTTON_ID)
+      .label("Skip")
+      .style(ButtonStyle::Primary),
+    CreateButton::new(STOP_BUTTON_ID)
+      .label("Stop")
+      .style(ButtonStyle::Danger),
+    CreateButton::new(LOOP_BUTTON_ID)
+      .label(format!("Loop: {}", loop_mode))
+      .style(ButtonStyle::Secondary),
+  ];
+  if let Some(url) = metadata.url {
+    controls.push(CreateButton::new_link(url).label("Open in browser"));
+  }
+
+  (embed, vec![CreateActionRow::Buttons(controls)])
+}
+
+fn progress_bar(position: Duration, duration: Duration) -> String {
+  let filled = match duration.as_secs_f64() > 0.0 {
+    true => {
+      ((position.as_secs_f64() / duration.as_secs_f64()) * PROGRESS_BAR_LENGTH as f64) as usize
+    }
+    false => 0,
+  }
+  .min(PROGRESS_BAR_LENGTH - 1);
+
+  format!(
+    "{}🔘{}",
+    "▬".repeat(filled),
+    "▬".repeat(PROGRESS_BAR_LENGTH - 1 - filled)
+  )
+}
+
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction) -> Result<(), Error> {
+  let guild_id = match component.guild_id {
+    Some(g) => g,
+    None => {
+      return component_text_response(ctx, component, "Error getting guild information").await
+    }
+  };
+
+  let custom_id = component.data.custom_id.as_str();
+  let restricted = match custom_id {
+    SKIP_BUTTON_ID => Some("skip"),
+    STOP_BUTTON_ID => Some("stop"),
+    _ => None,
+  };
+  if let (Some(name), Some(member)) = (restricted, &component.member) {
+    if let Err(reason) =
+      permissions::check_rules(ctx, guild_id, member, component.user.id, name).await
+    {
+      return permissions::deny_component(ctx, component, reason).await;
+    }
+  }
+
+  let voip_data = match VOIPData::from_user(ctx, guild_id, component.user.id) {
+    Ok(v) => v,
+    Err(s) => return component_text_response(ctx, component, s).await,
+  };
+
+  let manager = match songbird::get(ctx).await {
+    Some(arc) => arc.clone(),
+    None => {
+      error!("Error with songbird client");
+      return component_text_response(ctx, component, "Error getting voice client").await;
+    }
+  };
+
+  let handler_lock = match manager.get(guild_id) {
+    Some(h) => {
+      if let Err(s) = voip_data.reconcile_call(ctx, &h).await {
+        return component_text_response(ctx, component, s).await;
+      }
+      h
+    }
+    None => return component_text_response(ctx, component, "Not in a voice channel").await,
+  };
+
+  let handler = handler_lock.lock().await;
+
+  let result = match custom_id {
+    PAUSE_BUTTON_ID => Pause::pause_current(&handler).map(|_| ()),
+    RESUME_BUTTON_ID => Resume::resume_current(&handler).map(|_| ()),
+    SKIP_BUTTON_ID => match Skip::vote(
+      ctx,
+      guild_id,
+      component.member.as_ref(),
+      component.user.id,
+      &handler,
+    )
+    .await
+    {
+      Ok(SkipOutcome::Voted {
+        votes, required, ..
+      }) => {
+        drop(handler);
+        let text = format!("Voted to skip ({}/{})", votes, required);
+        return component_text_response(ctx, component, text).await;
+      }
+      outcome => outcome.map(|_| ()),
+    },
+    STOP_BUTTON_ID => {
+      component
+        .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+        .await?;
+      Stop::stop_playback(ctx, guild_id, &handler).await;
+      return Ok(());
+    }
+    LOOP_BUTTON_ID => {
+      let mode = read_guild_state(ctx, guild_id, |s| s.loop_mode)
+        .await
+        .next();
+      Loop::set_mode(ctx, guild_id, &handler, mode).await;
+      Ok(())
+    }
+    _ => Err("Unknown control"),
+  };
+
+  let current = handler.queue().current();
+  drop(handler);
+
+  if let Err(s) = result {
+    return component_text_response(ctx, component, s).await;
+  }
+
+  let response = match (custom_id, current) {
+    (SKIP_BUTTON_ID, _) | (_, None) => CreateInteractionResponse::Acknowledge,
+    (_, Some(current)) => {
+      let (embed, components) = render(ctx, guild_id, &current).await;
+      CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+          .embed(embed)
+          .components(components),
+      )
+    }
+  };
+
+  component.create_response(&ctx.http, response).await
+}
@@ -0,0 +1,120 @@
+use crate::commands::{cmd, playback::guild_settings, Command};
+use crate::constants::EMBED_COLOUR;
+use crate::storage::{database, PermissionTarget};
+use serenity::builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::client::Context;
+use serenity::model::application::{
+  CommandInteraction, ComponentInteraction, ResolvedOption, ResolvedValue,
+};
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, UserId};
+use serenity::Error;
+use tracing::error;
+
+// Commands and queue subcommands that can be limited to certain roles or users
+pub const RESTRICTABLE: [&str; 6] = ["stop", "skip", "remove", "clear", "volume", "filter"];
+
+// Commands that do what a restrictable one does along the way fall under its rules as well
+const IMPLIED: [(&str, &[&str]); 2] = [("jump", &["skip", "clear"]), ("leave", &["stop"])];
+
+fn restricted_names(command: &CommandInteraction) -> Vec<&'static str> {
+  let name = command.data.name.as_str();
+  let name = match name == cmd::Queue::name() {
+    true => match command.data.options().into_iter().next() {
+      Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(_),
+        ..
+      }) => name,
+      _ => return vec![],
+    },
+    false => name,
+  };
+
+  if let Some((_, names)) = IMPLIED.iter().find(|(n, _)| *n == name) {
+    return names.to_vec();
+  }
+  RESTRICTABLE.into_iter().filter(|r| *r == name).collect()
+}
+
+pub async fn check(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+  let (guild_id, member) = match (command.guild_id, &command.member) {
+    (Some(g), Some(m)) => (g, m),
+    _ => return Ok(()),
+  };
+  for name in restricted_names(command) {
+    check_rules(ctx, guild_id, member, command.user.id, name).await?;
+  }
+  Ok(())
+}
+
+// Commands without rules stay open to everyone, server managers and the DJ role always pass
+pub async fn check_rules(
+  ctx: &Context,
+  guild_id: GuildId,
+  member: &Member,
+  user_id: UserId,
+  name: &str,
+) -> Result<(), String> {
+  if member.permissions.is_some_and(|p| p.manage_guild()) {
+    return Ok(());
+  }
+
+  let targets = match database(ctx).await.command_permissions(guild_id).await {
+    Ok(p) => p
+      .into_iter()
+      .filter(|p| p.command == name)
+      .map(|p| p.target)
+      .collect::<Vec<_>>(),
+    Err(e) => {
+      error!("Error reading command permissions: {}", e);
+      return Err("Couldn't check your permissions, try again later".to_string());
+    }
+  };
+  if targets.is_empty() {
+    return Ok(());
+  }
+
+  let dj_role_id = guild_settings(ctx, guild_id).await.dj_role_id;
+  let allowed = dj_role_id.is_some_and(|r| member.roles.contains(&r))
+    || targets.iter().any(|t| match t {
+      PermissionTarget::Role(r) => member.roles.contains(r),
+      PermissionTarget::User(u) => *u == user_id,
+    });
+
+  match allowed {
+    true => Ok(()),
+    false => Err(format!(
+      "Only {} can use {}",
+      targets
+        .iter()
+        .map(|t| t.mention())
+        .collect::<Vec<_>>()
+        .join(", "),
+      name
+    )),
+  }
+}
+
+// Buttons don't go through the command handler, so their refusals only show to whoever clicked
+pub async fn deny_component(
+  ctx: &Context,
+  component: &ComponentInteraction,
+  reason: String,
+) -> Result<(), Error> {
+  component
+    .create_response(
+      &ctx.http,
+      CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+          .embed(
+            CreateEmbed::new()
+              .title("Permission denied")
+              .description(reason)
+              .colour(EMBED_COLOUR),
+          )
+          .ephemeral(true),
+      ),
+    )
+    .await
+}
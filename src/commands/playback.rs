@@ -1,18 +1,29 @@
-use crate::constants::placeholder_img;
+use crate::commands::crossfade::MAX_CROSSFADE_SECS;
+use crate::commands::events::{track_started, SongEnd, SongStart};
+use crate::commands::{connection, direct};
+use crate::constants::{placeholder_img, HttpClient};
+use crate::filters::{FilteredSource, MeasuredGain};
+use crate::state::{modify_guild_state, read_guild_state, LoopMode};
+use crate::storage::{database, ChannelPolicy, GuildSettings, HistoryEntry, PlaylistTrack};
 use regex::Regex;
+use serenity::builder::CreateEmbedFooter;
 use serenity::client::Context;
 use serenity::model::application::CommandInteraction;
-use serenity::model::id::ChannelId;
+use serenity::model::id::{ChannelId, UserId};
 use serenity::model::prelude::GuildId;
 use serenity::prelude::Mutex;
 use songbird::{
-  input::{Compose, YoutubeDl},
-  tracks::TrackHandle,
+  events::Event,
+  input::{AuxMetadata, Compose, HttpRequest, YoutubeDl},
+  tracks::{Track, TrackHandle},
   typemap::TypeMapKey,
+  Call, TrackEvent,
 };
 use std::{sync::Arc, time::Duration};
 use tracing::error;
 
+const PRELOAD_OFFSET: Duration = Duration::from_secs(5);
+
 pub struct VOIPData {
   pub channel_id: ChannelId,
   pub guild_id: GuildId,
@@ -29,13 +40,18 @@ impl VOIPData {
       }
     };
 
+    let user_id = command.member.as_ref().unwrap().user.id;
+    Self::from_user(ctx, guild_id, user_id)
+  }
+
+  pub fn from_user(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Result<VOIPData, String> {
     let guild_cache = guild_id.to_guild_cached(&ctx.cache);
 
     let channel_id = match guild_cache {
       Some(guild) => {
         let ch = guild
           .voice_states
-          .get(&command.member.as_ref().unwrap().user.id.clone())
+          .get(&user_id)
           .and_then(|vs| vs.channel_id);
 
         match ch {
@@ -67,6 +83,54 @@ impl VOIPData {
       .map_or(0u64, |id| id.0.into())
       == self.channel_id.get()
   }
+
+  // Applies the guild's channel policy when the user and the call are in different channels,
+  // only for commands that start or control playback
+  pub async fn reconcile_call(
+    &self,
+    ctx: &Context,
+    call: &Arc<Mutex<songbird::Call>>,
+  ) -> Result<(), String> {
+    if self.compare_to_call(call).await {
+      return Ok(());
+    }
+
+    let (current, listeners) = {
+      let call = call.lock().await;
+      (
+        call.current_channel().map(|c| ChannelId::new(c.0.get())),
+        listener_count(ctx, self.guild_id, &call),
+      )
+    };
+    let policy = guild_settings(ctx, self.guild_id).await.channel_policy;
+    if policy == ChannelPolicy::Locked && listeners > 0 {
+      let name = current.and_then(|c| {
+        self
+          .guild_id
+          .to_guild_cached(&ctx.cache)
+          .and_then(|g| g.channels.get(&c).map(|c| c.name.clone()))
+      });
+      return Err(match name {
+        Some(n) => format!("Already playing in {} for others, join them there", n),
+        None => "You're not in the voice channel".to_string(),
+      });
+    }
+
+    let manager = match songbird::get(ctx).await {
+      Some(arc) => arc.clone(),
+      None => {
+        error!("Error with songbird client");
+        return Err("Error getting voice client".to_string());
+      }
+    };
+    match connection::move_to(ctx, &manager, self).await {
+      Ok(_) => Ok(()),
+      Err(e) => {
+        error!("Error moving to voice channel: {}", e);
+        Err("Couldn't move to your channel".to_string())
+      }
+    }
+  }
 }
 
 #[derive(Clone)]
@@ -75,6 +139,104 @@ pub struct SongMetadata {
   pub thumbnail: String,
   pub duration: Duration,
   pub url: Option<String>,
+  pub requester: Option<Requester>,
+  pub source_kind: SourceKind,
+  pub volume: Option<u16>,
+}
+
+#[derive(Clone)]
+pub struct Requester {
+  pub user_id: UserId,
+  pub display_name: String,
+  pub enqueued_at: i64,
+}
+
+impl Requester {
+  pub fn from_command(command: &CommandInteraction) -> Self {
+    let display_name = match &command.member {
+      Some(member) => member.display_name().to_string(),
+      None => command.user.display_name().to_string(),
+    };
+
+    Self {
+      user_id: command.user.id,
+      display_name,
+      enqueued_at: chrono::Utc::now().timestamp(),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum SourceKind {
+  Search,
+  #[default]
+  Url,
+  // A link straight to an audio file, streamed without yt-dlp
+  Direct,
+  Attachment,
+}
+
+impl SourceKind {
+  pub const ALL: [SourceKind; 4] = [
+    SourceKind::Search,
+    SourceKind::Url,
+    SourceKind::Direct,
+    SourceKind::Attachment,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Search => "search",
+      Self::Url => "url",
+      Self::Direct => "direct",
+      Self::Attachment => "attachment",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|k| k.name() == name)
+  }
+
+  // Rows saved before the kind was stored go through yt-dlp, which can play file links too
+  fn from_saved(name: Option<String>) -> Self {
+    name
+      .as_deref()
+      .and_then(Self::from_name)
+      .unwrap_or_default()
+  }
+
+  pub fn of(param: &str) -> Self {
+    match param.contains("https://") || param.contains("http://") {
+      true => Self::Url,
+      false => Self::Search,
+    }
+  }
+
+  pub async fn detect(client: &HttpClient, param: &str) -> Self {
+    match Self::of(param) {
+      Self::Url if direct::is_direct_media(client, param).await => Self::Direct,
+      kind => kind,
+    }
+  }
+
+  pub fn source(&self, client: HttpClient, param: String) -> Box<dyn Compose> {
+    match self {
+      Self::Search => Box::new(YoutubeDl::new_search(client, param)),
+      Self::Url => Box::new(YoutubeDl::new(client, param)),
+      Self::Direct | Self::Attachment => Box::new(HttpRequest::new(client, param)),
+    }
+  }
+}
+
+impl std::fmt::Display for SourceKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Self::Search => write!(f, "search"),
+      Self::Url => write!(f, "link"),
+      Self::Direct => write!(f, "file link"),
+      Self::Attachment => write!(f, "attachment"),
+    }
+  }
 }
 
 pub struct SongMetadataKey;
@@ -83,21 +245,38 @@ impl TypeMapKey for SongMetadataKey {
   type Value = SongMetadata;
 }
 
+pub struct MeasuredGainKey;
+
+impl TypeMapKey for MeasuredGainKey {
+  type Value = MeasuredGain;
+}
+
+pub struct TrackRemovedKey;
+
+impl TypeMapKey for TrackRemovedKey {
+  type Value = ();
+}
+
 impl SongMetadata {
-  pub async fn from_source(source: &mut YoutubeDl) -> Self {
-    let metadata = match source.aux_metadata().await {
-      Ok(m) => m,
+  pub async fn from_source(source: &mut dyn Compose) -> Self {
+    match source.aux_metadata().await {
+      Ok(m) => Self::from_aux(&m),
       Err(e) => {
         error!("Error getting metadata: {}", e);
-        return Self {
+        Self {
           title: "N/A".to_string(),
           thumbnail: placeholder_img(),
           duration: Duration::default(),
           url: None,
-        };
+          requester: None,
+          source_kind: SourceKind::default(),
+          volume: None,
+        }
       }
-    };
+    }
+  }
 
+  pub fn from_aux(metadata: &AuxMetadata) -> Self {
     let thumbnail = metadata.thumbnail.clone().unwrap_or_else(placeholder_img);
 
     let title = metadata.title.clone().unwrap_or_else(|| "N/A".to_string());
@@ -111,6 +290,64 @@ impl SongMetadata {
       thumbnail,
       duration,
       url,
+      requester: None,
+      source_kind: SourceKind::default(),
+      volume: None,
+    }
+  }
+
+  pub fn from_playlist_entry(entry: &serde_json::Value) -> Option<Self> {
+    let url = entry["url"]
+      .as_str()
+      .or_else(|| entry["webpage_url"].as_str())?
+      .to_string();
+
+    let title = entry["title"].as_str().unwrap_or("N/A").to_string();
+
+    let thumbnail = entry["thumbnails"]
+      .as_array()
+      .and_then(|t| t.last())
+      .and_then(|t| t["url"].as_str())
+      .map(|t| t.to_string())
+      .unwrap_or_else(placeholder_img);
+
+    let duration = entry["duration"]
+      .as_f64()
+      .map(Duration::from_secs_f64)
+      .unwrap_or_default();
+
+    Some(Self {
+      title,
+      thumbnail,
+      duration,
+      url: Some(url),
+      requester: None,
+      source_kind: SourceKind::Url,
+      volume: None,
+    })
+  }
+
+  pub fn from_history(entry: HistoryEntry) -> Self {
+    Self {
+      title: entry.title,
+      thumbnail: entry.thumbnail.unwrap_or_else(placeholder_img),
+      duration: entry.duration,
+      url: Some(entry.url),
+      requester: None,
+      source_kind: SourceKind::from_saved(entry.source_kind),
+      volume: None,
+    }
+  }
+
+  pub fn from_saved(track: PlaylistTrack) -> Self {
+    Self {
+      title: track.title,
+      thumbnail: track.thumbnail.unwrap_or_else(placeholder_img),
+      duration: track.duration,
+      url: Some(track.url),
+      requester: None,
+      source_kind: SourceKind::from_saved(track.source_kind),
+      volume: None,
     }
   }
 
@@ -121,13 +358,355 @@ impl SongMetadata {
       .expect("Metadata not found")
       .clone()
   }
+
+  // What's worth keeping past the session, uploaded files and their cover art won't load later
+  pub fn saved_url(&self) -> Option<String> {
+    self
+      .url
+      .clone()
+      .filter(|u| self.source_kind != SourceKind::Attachment && !direct::is_expiring(u))
+  }
+
+  pub fn saved_thumbnail(&self) -> Option<String> {
+    Some(self.thumbnail.clone()).filter(|t| !direct::is_expiring(t))
+  }
+
+  // Searches are kept as the link they found, so only the kind decides between yt-dlp and a plain stream
+  pub fn source(&self, client: HttpClient) -> Option<Box<dyn Compose>> {
+    let url = self.url.clone()?;
+    let kind = match self.source_kind {
+      SourceKind::Search => SourceKind::Url,
+      kind => kind,
+    };
+    Some(kind.source(client, url))
+  }
+
+  pub fn requested_by(&self) -> Option<String> {
+    self.requester.as_ref().map(|r| {
+      format!(
+        "<@{}> <t:{}:R> via {}",
+        r.user_id, r.enqueued_at, self.source_kind
+      )
+    })
+  }
 }
 
-pub fn get_source(client: crate::constants::HttpClient, param: String) -> YoutubeDl {
-  if param.contains("https://") {
-    YoutubeDl::new(client, param)
+pub async fn enqueue_track(
+  ctx: &Context,
+  call: &mut Call,
+  source: Box<dyn Compose>,
+  metadata: SongMetadata,
+  channel_id: ChannelId,
+  guild_id: GuildId,
+) -> TrackHandle {
+  // Loading the settings also syncs the guild's normalisation toggle into its filters
+  let settings = guild_settings(ctx, guild_id).await;
+  let volume = metadata.volume.unwrap_or(settings.volume);
+  let filters = modify_guild_state(ctx, guild_id, |s| s.filters.clone()).await;
+  let preload_time = match metadata.duration.is_zero() {
+    true => None,
+    // Early enough for the longest crossfade to start from a ready input
+    false => Some(
+      output_length(ctx, guild_id, metadata.duration)
+        .await
+        .saturating_sub(PRELOAD_OFFSET + Duration::from_secs(MAX_CROSSFADE_SECS.into())),
+    ),
+  };
+  let gain = MeasuredGain::new();
+  let track =
+    Track::from(FilteredSource::new(source, filters, gain.clone())).volume(volume_scale(volume));
+  let handle = call.enqueue_with_preload(track, preload_time);
+  {
+    let mut data = handle.typemap().write().await;
+    data.insert::<SongMetadataKey>(metadata);
+    data.insert::<MeasuredGainKey>(gain);
+  }
+
+  if read_guild_state(ctx, guild_id, |s| s.loop_mode).await == LoopMode::Track {
+    if let Err(e) = handle.enable_loop() {
+      error!("Error enabling loop: {}", e);
+    }
+  }
+
+  if call.queue().len() == 1 {
+    let ctx = ctx.clone();
+    let handle = handle.clone();
+    tokio::spawn(async move { track_started(&ctx, guild_id, channel_id, &handle).await });
   } else {
-    YoutubeDl::new_search(client, param)
+    notify_start(ctx, &handle, channel_id, guild_id);
+  }
+
+  match handle.add_event(
+    Event::Track(TrackEvent::End),
+    SongEnd {
+      channel_id,
+      guild_id,
+      ctx: ctx.clone(),
+    },
+  ) {
+    Ok(_) => (),
+    Err(e) => error!("Error adding SongEnd event: {}", e),
+  }
+
+  handle
+}
+
+fn notify_start(ctx: &Context, handle: &TrackHandle, channel_id: ChannelId, guild_id: GuildId) {
+  match handle.add_event(
+    Event::Track(TrackEvent::Play),
+    SongStart {
+      channel_id,
+      guild_id,
+      ctx: ctx.clone(),
+    },
+  ) {
+    Ok(_) => (),
+    Err(e) => error!("Error adding SongStart event: {}", e),
+  }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum QueuePosition {
+  #[default]
+  End,
+  Next,
+  Now,
+}
+
+impl QueuePosition {
+  pub const ALL: [QueuePosition; 3] = [QueuePosition::End, QueuePosition::Next, QueuePosition::Now];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::End => "end",
+      Self::Next => "next",
+      Self::Now => "now",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|p| p.name() == name)
+  }
+}
+
+// Moves the last `count` queued tracks, which enqueue_track always appends, to the front
+pub fn reposition_added(
+  ctx: &Context,
+  call: &Call,
+  count: usize,
+  position: QueuePosition,
+  channel_id: ChannelId,
+  guild_id: GuildId,
+) {
+  if position == QueuePosition::End || count == 0 {
+    return;
+  }
+
+  let interrupted = call.queue().modify_queue(|queue| {
+    let len = queue.len();
+    if count >= len {
+      return None;
+    }
+
+    let added = queue.drain(len - count..).collect::<Vec<_>>();
+    let (index, interrupted) = match position {
+      QueuePosition::Now => {
+        let current = queue.front().map(|current| current.handle());
+        if let Some(Err(e)) = current.as_ref().map(|current| current.pause()) {
+          error!("Error pausing interrupted track: {}", e);
+        }
+        (0, current)
+      }
+      _ => (1, None),
+    };
+    for (i, track) in added.into_iter().enumerate() {
+      queue.insert(index + i, track);
+    }
+    interrupted
+  });
+
+  if let Some(interrupted) = interrupted {
+    // Its start has already been announced, this has it announced again once it resumes
+    notify_start(ctx, &interrupted, channel_id, guild_id);
+    if let Err(e) = call.queue().resume() {
+      error!("Error starting track: {}", e);
+    }
+  }
+}
+
+pub async fn discard_track(handle: &TrackHandle) {
+  {
+    let mut data = handle.typemap().write().await;
+    data.insert::<TrackRemovedKey>(());
+  }
+  if let Err(e) = handle.stop() {
+    error!("Error stopping removed track: {}", e);
+  }
+}
+
+// Humans in the call's channel, the bot itself and other bots don't count
+pub fn listener_count(ctx: &Context, guild_id: GuildId, call: &Call) -> usize {
+  let channel_id = match call.current_channel() {
+    Some(c) => ChannelId::new(c.0.get()),
+    None => return 0,
+  };
+
+  match guild_id.to_guild_cached(&ctx.cache) {
+    Some(guild) => guild
+      .voice_states
+      .values()
+      .filter(|vs| vs.channel_id == Some(channel_id))
+      .filter(|vs| {
+        let bot = match &vs.member {
+          Some(member) => member.user.bot,
+          None => ctx.cache.user(vs.user_id).is_some_and(|u| u.bot),
+        };
+        !bot
+      })
+      .count(),
+    None => 0,
+  }
+}
+
+pub async fn guild_settings(ctx: &Context, guild_id: GuildId) -> GuildSettings {
+  if let Some(settings) = read_guild_state(ctx, guild_id, |s| s.settings.clone()).await {
+    return settings;
+  }
+
+  let settings = match database(ctx).await.guild_settings(guild_id).await {
+    Ok(s) => s,
+    Err(e) => {
+      error!("Error reading guild settings: {}", e);
+      return GuildSettings::default();
+    }
+  };
+  cache_guild_settings(ctx, guild_id, settings.clone()).await;
+  settings
+}
+
+pub async fn save_guild_settings(
+  ctx: &Context,
+  guild_id: GuildId,
+  settings: GuildSettings,
+) -> crate::storage::Result<()> {
+  database(ctx)
+    .await
+    .save_guild_settings(guild_id, settings.clone())
+    .await?;
+  cache_guild_settings(ctx, guild_id, settings).await;
+  Ok(())
+}
+
+async fn cache_guild_settings(ctx: &Context, guild_id: GuildId, settings: GuildSettings) {
+  modify_guild_state(ctx, guild_id, |s| {
+    s.filters
+      .write()
+      .unwrap_or_else(|e| e.into_inner())
+      .normalize = settings.normalize;
+    s.settings = Some(settings);
+  })
+  .await;
+}
+
+// Track positions count output time, which a speed preset stretches or squeezes
+pub async fn output_length(ctx: &Context, guild_id: GuildId, length: Duration) -> Duration {
+  read_guild_state(ctx, guild_id, |s| {
+    s.filters
+      .read()
+      .unwrap_or_else(|e| e.into_inner())
+      .output_length(length)
+  })
+  .await
+}
+
+pub async fn guild_volume(ctx: &Context, guild_id: GuildId) -> u16 {
+  guild_settings(ctx, guild_id).await.volume
+}
+
+pub fn volume_scale(percent: u16) -> f32 {
+  f32::from(percent) / 100.0
+}
+
+pub async fn measured_gain(handle: &TrackHandle) -> Option<f32> {
+  let data = handle.typemap().read().await;
+  data.get::<MeasuredGainKey>().and_then(|g| g.get())
+}
+
+pub fn format_volume(track_volume: Option<u16>, guild_volume: u16) -> String {
+  match track_volume {
+    Some(v) => format!("{}% (this track only)", v),
+    None => format!("{}%", guild_volume),
+  }
+}
+
+pub fn queue_footer<D>(count: usize, length: D, loop_mode: LoopMode) -> CreateEmbedFooter
+where
+  D: std::fmt::Display,
+{
+  let text = match loop_mode {
+    LoopMode::Off => format!("{} songs in queue - {}", count, length),
+    mode => format!("{} songs in queue - {} - Looping {}", count, length, mode),
+  };
+  CreateEmbedFooter::new(text)
+}
+
+pub fn is_playlist_url(url: &str) -> bool {
+  let re = Regex::new(
+    r"^https?://((www|m|music)\.)?(youtube\.com/playlist\?(.+&)?list=|soundcloud\.com/[^/]+/sets/)",
+  )
+  .expect("Failed to compile regex");
+  re.is_match(url)
+}
+
+pub async fn expand_playlist(url: &str, limit: usize) -> Result<Vec<SongMetadata>, String> {
+  let output = match tokio::process::Command::new("yt-dlp")
+    .args([
+      "--flat-playlist",
+      "-J",
+      "--playlist-end",
+      &limit.to_string(),
+      url,
+    ])
+    .output()
+    .await
+  {
+    Ok(o) => o,
+    Err(e) => {
+      error!("Error running yt-dlp: {}", e);
+      return Err("Couldn't load playlist".to_string());
+    }
+  };
+
+  if !output.status.success() {
+    error!(
+      "yt-dlp failed expanding playlist: {}",
+      String::from_utf8_lossy(&output.stderr)
+    );
+    return Err("Couldn't load playlist".to_string());
+  }
+
+  let playlist: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+    Ok(p) => p,
+    Err(e) => {
+      error!("Error parsing playlist: {}", e);
+      return Err("Couldn't load playlist".to_string());
+    }
+  };
+
+  let entries = playlist["entries"]
+    .as_array()
+    .map(|entries| {
+      entries
+        .iter()
+        .take(limit)
+        .filter_map(SongMetadata::from_playlist_entry)
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+
+  match entries.is_empty() {
+    true => Err("Playlist is empty".to_string()),
+    false => Ok(entries),
   }
 }
 
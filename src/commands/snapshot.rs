@@ -0,0 +1,217 @@
+use crate::commands::connection;
+use crate::commands::playback::{enqueue_track, SongMetadata, VOIPData};
+use crate::constants::{HttpKey, EMBED_COLOUR};
+use crate::state::GuildStateStorage;
+use crate::storage::{database, DatabaseStorage, PlaylistTrack, QueueSnapshot};
+use serenity::{
+  builder::{CreateEmbed, CreateMessage},
+  client::Context,
+  model::id::{ChannelId, GuildId},
+  prelude::{RwLock, TypeMap},
+};
+use songbird::Songbird;
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Duration,
+};
+use tracing::{error, info};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(3600);
+
+// Snapshots from the previous run must not be overwritten before they've been restored
+static RESTORED: AtomicBool = AtomicBool::new(false);
+
+pub async fn save_queues(manager: &Songbird, data: &RwLock<TypeMap>) {
+  if !RESTORED.load(Ordering::SeqCst) {
+    return;
+  }
+
+  let (database, guild_states) = {
+    let data = data.read().await;
+    (
+      data
+        .get::<DatabaseStorage>()
+        .expect("No database in global storage")
+        .clone(),
+      data
+        .get::<GuildStateStorage>()
+        .expect("No guild state in global storage")
+        .clone(),
+    )
+  };
+
+  let mut snapshots = vec![];
+  for (guild_id, handler_lock) in manager.iter() {
+    let guild_id = GuildId::new(guild_id.0.get());
+    let (voice_channel_id, queue) = {
+      let handler = handler_lock.lock().await;
+      match handler.current_channel() {
+        Some(c) => (ChannelId::new(c.0.get()), handler.queue().current_queue()),
+        None => continue,
+      }
+    };
+
+    let current = match queue.first() {
+      Some(c) => c,
+      None => continue,
+    };
+    let mut position = match current.get_info().await {
+      Ok(state) => state.position,
+      Err(_) => Duration::default(),
+    };
+
+    let mut tracks = Vec::with_capacity(queue.len());
+    for (i, handle) in queue.iter().enumerate() {
+      let metadata = SongMetadata::from_handle(handle).await;
+      match metadata.saved_url() {
+        Some(url) => tracks.push(PlaylistTrack {
+          thumbnail: metadata.saved_thumbnail(),
+          title: metadata.title,
+          url,
+          duration: metadata.duration,
+          source_kind: Some(metadata.source_kind.name().to_string()),
+        }),
+        // The position belongs to the current track, not whichever ends up first
+        None if i == 0 => position = Duration::default(),
+        None => (),
+      }
+    }
+
+    let text_channel_id = guild_states
+      .read()
+      .await
+      .get(&guild_id)
+      .and_then(|s| s.now_playing.as_ref().map(|p| p.channel_id));
+
+    snapshots.push(QueueSnapshot {
+      guild_id,
+      voice_channel_id,
+      text_channel_id,
+      position,
+      tracks,
+    });
+  }
+
+  if let Err(e) = database.replace_queue_snapshots(snapshots).await {
+    error!("Error saving queue snapshots: {}", e);
+  }
+}
+
+pub async fn save_queues_periodically(manager: Arc<Songbird>, data: Arc<RwLock<TypeMap>>) {
+  loop {
+    tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+    save_queues(&manager, &data).await;
+  }
+}
+
+pub async fn restore_queues(ctx: &Context) {
+  if RESTORED.load(Ordering::SeqCst) {
+    return;
+  }
+
+  let snapshots = match database(ctx).await.queue_snapshots(SNAPSHOT_MAX_AGE).await {
+    Ok(s) => s,
+    Err(e) => {
+      error!("Error reading queue snapshots: {}", e);
+      vec![]
+    }
+  };
+
+  for snapshot in snapshots {
+    let guild_id = snapshot.guild_id;
+    match restore(ctx, snapshot).await {
+      Ok(count) => info!("Restored {} tracks in Guild({})", count, guild_id),
+      Err(s) => error!("Couldn't restore queue in Guild({}): {}", guild_id, s),
+    }
+  }
+
+  RESTORED.store(true, Ordering::SeqCst);
+}
+
+async fn restore(ctx: &Context, snapshot: QueueSnapshot) -> Result<usize, String> {
+  if snapshot.tracks.is_empty() {
+    return Ok(0);
+  }
+
+  let manager = match songbird::get(ctx).await {
+    Some(arc) => arc.clone(),
+    None => return Err("Error getting voice client".to_string()),
+  };
+
+  if manager.get(snapshot.guild_id).is_some() {
+    return Err("Already in a call".to_string());
+  }
+
+  let voip_data = VOIPData {
+    channel_id: snapshot.voice_channel_id,
+    guild_id: snapshot.guild_id,
+  };
+  let handler_lock = match connection::join(ctx, &manager, &voip_data).await {
+    Ok(h) => h,
+    Err(e) => return Err(format!("Error joining channel: {}", e)),
+  };
+
+  let http_client = {
+    let data = ctx.data.read().await;
+    data
+      .get::<HttpKey>()
+      .cloned()
+      .expect("HttpClient did not exist")
+  };
+
+  // Without a text channel the panel and notices go to the voice channel's chat
+  let text_channel_id = snapshot
+    .text_channel_id
+    .unwrap_or(snapshot.voice_channel_id);
+  let count = snapshot.tracks.len();
+
+  let mut tracks = Vec::with_capacity(count);
+  for track in snapshot.tracks {
+    let metadata = SongMetadata::from_saved(track);
+    if let Some(source) = metadata.source(http_client.clone()) {
+      tracks.push((source, metadata));
+    }
+  }
+
+  let mut handler = handler_lock.lock().await;
+  let mut first = None;
+  for (source, metadata) in tracks {
+    let handle = enqueue_track(
+      ctx,
+      &mut handler,
+      source,
+      metadata,
+      text_channel_id,
+      snapshot.guild_id,
+    )
+    .await;
+    first.get_or_insert(handle);
+  }
+  drop(handler);
+
+  if let Some(handle) = first.filter(|_| !snapshot.position.is_zero()) {
+    if let Err(e) = handle.seek(snapshot.position).result_async().await {
+      error!("Error seeking restored track: {}", e);
+    }
+  }
+
+  if let Err(e) = text_channel_id
+    .send_message(
+      &ctx.http,
+      CreateMessage::new().embed(
+        CreateEmbed::new()
+          .title(format!("Resumed {} tracks after a restart", count))
+          .colour(EMBED_COLOUR),
+      ),
+    )
+    .await
+  {
+    error!("Error sending resume notice: {}", e);
+  }
+
+  Ok(count)
+}
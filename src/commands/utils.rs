@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub fn remove_md_characters<S>(s: S) -> String
 where
   S: ToString,
@@ -12,3 +14,116 @@ where
     .replace('[', r"\[")
     .replace(']', r"\]")
 }
+
+pub fn truncate_unicode(text: &str, max_chars: usize) -> String {
+  match text.char_indices().nth(max_chars) {
+    None => text.to_string(),
+    Some((i, _)) => {
+      let mut valid_index = i;
+      while !text.is_char_boundary(valid_index) {
+        valid_index -= 1;
+      }
+      let trimmed = &text[..valid_index];
+      format!("{}...", trimmed)
+    }
+  }
+}
+
+// Accepts plain seconds ("90"), clock notation ("1:23", "1:02:03") and units ("1h2m3s")
+pub fn parse_duration(input: &str) -> Option<Duration> {
+  let input = input.trim().to_lowercase();
+  if input.is_empty() {
+    return None;
+  }
+
+  let seconds = if input.contains(':') {
+    let parts = input.split(':').collect::<Vec<_>>();
+    if parts.len() > 3 {
+      return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+      seconds = seconds * 60.0 + parse_part(part)?;
+    }
+    seconds
+  } else if input.contains(['h', 'm', 's']) {
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in input.chars().filter(|c| !c.is_whitespace()) {
+      let unit = match c {
+        'h' => 3600.0,
+        'm' => 60.0,
+        's' => 1.0,
+        _ => {
+          number.push(c);
+          continue;
+        }
+      };
+      seconds += parse_part(&number)? * unit;
+      number.clear();
+    }
+    if !number.is_empty() {
+      seconds += parse_part(&number)?;
+    }
+    seconds
+  } else {
+    parse_part(&input)?
+  };
+
+  Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn parse_part(part: &str) -> Option<f64> {
+  match part.trim().parse::<f64>() {
+    Ok(n) if n.is_finite() && n.is_sign_positive() => Some(n),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::parse_duration;
+  use std::time::Duration;
+
+  #[test]
+  fn parses_clock_notation() {
+    assert_eq!(parse_duration("1:23"), Some(Duration::from_secs(83)));
+    assert_eq!(parse_duration("1:02:03"), Some(Duration::from_secs(3723)));
+    assert_eq!(parse_duration("0:1.5"), Some(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("1:2:3:4"), None);
+    assert_eq!(parse_duration("1:"), None);
+  }
+
+  #[test]
+  fn parses_units() {
+    assert_eq!(parse_duration("1h2m3s"), Some(Duration::from_secs(3723)));
+    assert_eq!(parse_duration("2m 30s"), Some(Duration::from_secs(150)));
+    assert_eq!(parse_duration("1m30"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("5M"), Some(Duration::from_secs(300)));
+    assert_eq!(parse_duration("hms"), None);
+  }
+
+  #[test]
+  fn parses_bare_seconds() {
+    assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration(" 2.5 "), Some(Duration::from_millis(2500)));
+    assert_eq!(parse_duration(""), None);
+    assert_eq!(parse_duration("abc"), None);
+  }
+
+  #[test]
+  fn rejects_overflow() {
+    assert_eq!(parse_duration("1e20"), None);
+    assert_eq!(parse_duration("99999999999999999999h"), None);
+    assert_eq!(parse_duration("inf"), None);
+    assert_eq!(parse_duration("nan"), None);
+  }
+
+  #[test]
+  fn rejects_negative_parts() {
+    assert_eq!(parse_duration("-5"), None);
+    assert_eq!(parse_duration("1:-5"), None);
+    assert_eq!(parse_duration("1m-5s"), None);
+    assert_eq!(parse_duration("-0"), None);
+  }
+}
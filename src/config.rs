@@ -3,9 +3,13 @@ use serenity::{
   model::id::{ApplicationId, GuildId},
   prelude::TypeMapKey,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tracing::{error, info};
 
+const DEFAULT_PLAYLIST_LIMIT: usize = 50;
+const DEFAULT_DATABASE_PATH: &str = "capybara.db";
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+
 pub struct ConfigStorage;
 
 impl TypeMapKey for ConfigStorage {
@@ -16,6 +20,10 @@ pub struct Config {
   pub token: String,
   pub application_id: ApplicationId,
   pub guild_id: Option<GuildId>,
+  pub playlist_limit: usize,
+  pub database_path: String,
+  // Zero keeps the bot in the channel while idle
+  pub idle_timeout: Duration,
 }
 
 pub fn read_config() -> Config {
@@ -52,9 +60,52 @@ pub fn read_config() -> Config {
     }
   };
 
+  let playlist_limit = match std::env::var("PLAYLIST_LIMIT") {
+    Ok(limit) => match limit.parse::<usize>() {
+      Ok(0) => {
+        error!("PLAYLIST_LIMIT must be at least 1, using default");
+        DEFAULT_PLAYLIST_LIMIT
+      }
+      Ok(l) => l,
+      Err(e) => {
+        error!("Error parsing PLAYLIST_LIMIT({}), using default", limit);
+        error!("ParseError: {:?}", e);
+        DEFAULT_PLAYLIST_LIMIT
+      }
+    },
+    Err(_e) => DEFAULT_PLAYLIST_LIMIT,
+  };
+  info!("Playlists are limited to {} tracks", playlist_limit);
+
+  let database_path =
+    std::env::var("DATABASE_PATH").unwrap_or_else(|_e| DEFAULT_DATABASE_PATH.to_string());
+  info!("Using database at {}", database_path);
+
+  let idle_timeout = match std::env::var("IDLE_TIMEOUT") {
+    Ok(secs) => match secs.parse::<u64>() {
+      Ok(s) => Duration::from_secs(s),
+      Err(e) => {
+        error!("Error parsing IDLE_TIMEOUT({}), using default", secs);
+        error!("ParseError: {:?}", e);
+        Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS)
+      }
+    },
+    Err(_e) => Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+  };
+  match idle_timeout.is_zero() {
+    true => info!("Leaving idle voice channels is disabled"),
+    false => info!(
+      "Leaving voice channels after {}s idle",
+      idle_timeout.as_secs()
+    ),
+  }
+
   Config {
     token,
     application_id,
     guild_id,
+    playlist_limit,
+    database_path,
+    idle_timeout,
   }
 }
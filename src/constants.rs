@@ -4,6 +4,7 @@ use serenity::prelude::TypeMapKey;
 
 pub enum ErrorCodes {
   ConfigFileError = 10,
+  DatabaseError = 11,
 }
 
 pub fn placeholder_img() -> String {
@@ -0,0 +1,629 @@
+use serenity::async_trait;
+use songbird::input::{
+  codecs::{get_codec_registry, get_probe},
+  AudioStream, AudioStreamError, AuxMetadata, Compose, Input,
+};
+use std::{
+  f32::consts::PI,
+  io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom},
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, RwLock,
+  },
+  time::Duration,
+};
+use symphonia::core::{
+  audio::SampleBuffer,
+  codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+  errors::Error as SymphoniaError,
+  formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+  io::{MediaSource, MediaSourceStream},
+  meta::MetadataOptions,
+  units::Time,
+};
+
+pub const EQ_BANDS: [u32; 10] = [32, 64, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+pub const MAX_EQ_GAIN: f32 = 12.0;
+
+const EQ_Q: f32 = 1.0;
+const ROTATION_PERIOD_SECS: f32 = 8.0;
+
+// Running RMS normalisation towards roughly -16 dBFS, within +-12 dB
+const TARGET_RMS: f32 = 0.158;
+const LOUDNESS_WINDOW_SECS: f32 = 3.0;
+const GAIN_SMOOTHING_SECS: f32 = 0.5;
+const MAX_NORMALIZE_DB: f32 = 12.0;
+const SILENCE_RMS: f32 = 0.001;
+
+// Songbird's raw PCM format: a magic string followed by the sample rate and channel count
+const RAW_MAGIC: &[u8; 8] = b"SbirdRaw";
+const HEADER_LEN: u64 = 16;
+const SAMPLE_LEN: u64 = std::mem::size_of::<f32>() as u64;
+
+pub type SharedFilters = Arc<RwLock<FilterSettings>>;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum FilterPreset {
+  #[default]
+  Off,
+  BassBoost,
+  Nightcore,
+  Vaporwave,
+  EightD,
+  Karaoke,
+}
+
+impl FilterPreset {
+  pub const ALL: [FilterPreset; 6] = [
+    FilterPreset::BassBoost,
+    FilterPreset::Nightcore,
+    FilterPreset::Vaporwave,
+    FilterPreset::EightD,
+    FilterPreset::Karaoke,
+    FilterPreset::Off,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Off => "off",
+      Self::BassBoost => "bassboost",
+      Self::Nightcore => "nightcore",
+      Self::Vaporwave => "vaporwave",
+      Self::EightD => "8d",
+      Self::Karaoke => "karaoke",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|p| p.name() == name)
+  }
+
+  fn speed(&self) -> f32 {
+    match self {
+      Self::Nightcore => 1.25,
+      Self::Vaporwave => 0.8,
+      _ => 1.0,
+    }
+  }
+
+  fn equalizer(&self) -> [f32; EQ_BANDS.len()] {
+    match self {
+      Self::BassBoost => [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+      Self::Vaporwave => [2.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0, -2.0, -3.0],
+      _ => [0.0; EQ_BANDS.len()],
+    }
+  }
+}
+
+impl std::fmt::Display for FilterPreset {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct FilterSettings {
+  pub preset: FilterPreset,
+  // Custom gain per band in dB, added on top of the preset
+  pub equalizer: [f32; EQ_BANDS.len()],
+  pub normalize: bool,
+}
+
+impl FilterSettings {
+  pub fn is_active(&self) -> bool {
+    self.preset != FilterPreset::Off || self.equalizer.iter().any(|g| *g != 0.0)
+  }
+
+  // How long a source of the given length takes to play out at the preset's speed
+  pub fn output_length(&self, length: Duration) -> Duration {
+    length.div_f32(self.preset.speed())
+  }
+
+  fn gains(&self) -> [f32; EQ_BANDS.len()] {
+    let mut gains = self.preset.equalizer();
+    for (gain, custom) in gains.iter_mut().zip(self.equalizer) {
+      *gain = (*gain + custom).clamp(-MAX_EQ_GAIN * 2.0, MAX_EQ_GAIN * 2.0);
+    }
+    gains
+  }
+}
+
+pub fn band_name(frequency: u32) -> String {
+  match frequency >= 1000 {
+    true => format!("{} kHz", frequency / 1000),
+    false => format!("{} Hz", frequency),
+  }
+}
+
+// Gain the normaliser is currently applying to a track, in dB
+#[derive(Clone)]
+pub struct MeasuredGain(Arc<AtomicU32>);
+
+impl MeasuredGain {
+  pub fn new() -> Self {
+    Self(Arc::new(AtomicU32::new(f32::NAN.to_bits())))
+  }
+
+  pub fn get(&self) -> Option<f32> {
+    let gain = f32::from_bits(self.0.load(Ordering::Relaxed));
+    match gain.is_nan() {
+      true => None,
+      false => Some(gain),
+    }
+  }
+
+  fn set(&self, gain: f32) {
+    self.0.store(gain.to_bits(), Ordering::Relaxed);
+  }
+}
+
+// Wraps a source so its decoded PCM goes through the guild's filters before Opus encoding
+pub struct FilteredSource {
+  inner: Box<dyn Compose>,
+  filters: SharedFilters,
+  gain: MeasuredGain,
+}
+
+impl FilteredSource {
+  pub fn new(inner: Box<dyn Compose>, filters: SharedFilters, gain: MeasuredGain) -> Self {
+    Self {
+      inner,
+      filters,
+      gain,
+    }
+  }
+}
+
+impl From<FilteredSource> for Input {
+  fn from(val: FilteredSource) -> Self {
+    Input::Lazy(Box::new(val))
+  }
+}
+
+#[async_trait]
+impl Compose for FilteredSource {
+  fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+    let stream = self.inner.create()?;
+    FilterStream::new(stream, self.filters.clone(), self.gain.clone())
+      .map(FilterStream::into_audio_stream)
+  }
+
+  async fn create_async(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+    let stream = self.inner.create_async().await?;
+    let filters = self.filters.clone();
+    let gain = self.gain.clone();
+    match tokio::task::spawn_blocking(move || FilterStream::new(stream, filters, gain)).await {
+      Ok(result) => result.map(FilterStream::into_audio_stream),
+      Err(e) => Err(AudioStreamError::Fail(Box::new(e))),
+    }
+  }
+
+  fn should_create_async(&self) -> bool {
+    self.inner.should_create_async()
+  }
+
+  async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+    self.inner.aux_metadata().await
+  }
+}
+
+struct FilterStream {
+  format: Box<dyn FormatReader>,
+  decoder: Box<dyn Decoder>,
+  track_id: u32,
+  seekable: bool,
+  sample_rate: u32,
+  channels: usize,
+  header: [u8; HEADER_LEN as usize],
+  chain: FilterChain,
+  loudness: Loudness,
+  filters: SharedFilters,
+  // Filtered frames waiting to be resampled, and the fractional read position into them
+  pending: Vec<f32>,
+  cursor: f64,
+  output: Vec<u8>,
+  output_pos: usize,
+  position: u64,
+}
+
+impl FilterStream {
+  fn new(
+    stream: AudioStream<Box<dyn MediaSource>>,
+    filters: SharedFilters,
+    gain: MeasuredGain,
+  ) -> Result<Self, AudioStreamError> {
+    let seekable = stream.input.is_seekable();
+    let source = MediaSourceStream::new(stream.input, Default::default());
+    let probed = get_probe()
+      .format(
+        &stream.hint.unwrap_or_default(),
+        source,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+      )
+      .map_err(|e| AudioStreamError::Fail(Box::new(e)))?;
+
+    let format = probed.format;
+    let track = format
+      .tracks()
+      .iter()
+      .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+      .ok_or(AudioStreamError::Unsupported)?;
+    let track_id = track.id;
+    let decoder = get_codec_registry()
+      .make(&track.codec_params, &DecoderOptions::default())
+      .map_err(|e| AudioStreamError::Fail(Box::new(e)))?;
+
+    let mut stream = Self {
+      format,
+      decoder,
+      track_id,
+      seekable,
+      sample_rate: 0,
+      channels: 0,
+      header: [0; HEADER_LEN as usize],
+      chain: FilterChain::default(),
+      loudness: Loudness::new(gain),
+      filters,
+      pending: vec![],
+      cursor: 0.0,
+      output: vec![],
+      output_pos: 0,
+      position: 0,
+    };
+
+    // The raw header needs the decoded layout, which some containers only reveal in the first packet
+    let first = match stream.decode_next() {
+      Ok(Some(samples)) => samples,
+      Ok(None) => return Err(AudioStreamError::Unsupported),
+      Err(e) => return Err(AudioStreamError::Fail(Box::new(e))),
+    };
+    stream.header[..8].copy_from_slice(RAW_MAGIC);
+    stream.header[8..12].copy_from_slice(&stream.sample_rate.to_le_bytes());
+    stream.header[12..].copy_from_slice(&(stream.channels as u32).to_le_bytes());
+    stream.output.extend_from_slice(&stream.header);
+    stream.process(first);
+
+    Ok(stream)
+  }
+
+  fn into_audio_stream(self) -> AudioStream<Box<dyn MediaSource>> {
+    AudioStream {
+      input: Box::new(self),
+      hint: None,
+    }
+  }
+
+  fn decode_next(&mut self) -> IoResult<Option<Vec<f32>>> {
+    loop {
+      let packet = match self.format.next_packet() {
+        Ok(p) => p,
+        Err(SymphoniaError::IoError(e)) if e.kind() == IoErrorKind::UnexpectedEof => {
+          return Ok(None)
+        }
+        Err(SymphoniaError::ResetRequired) => {
+          self.decoder.reset();
+          continue;
+        }
+        Err(e) => return Err(IoError::other(e)),
+      };
+
+      if packet.track_id() != self.track_id {
+        continue;
+      }
+
+      let decoded = match self.decoder.decode(&packet) {
+        Ok(d) => d,
+        Err(SymphoniaError::DecodeError(_)) => continue,
+        Err(e) => return Err(IoError::other(e)),
+      };
+
+      let spec = *decoded.spec();
+      if self.channels == 0 {
+        self.sample_rate = spec.rate;
+        self.channels = spec.channels.count();
+      }
+      if spec.channels.count() != self.channels || decoded.frames() == 0 {
+        continue;
+      }
+
+      let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+      buffer.copy_interleaved_ref(decoded);
+      return Ok(Some(buffer.samples().to_vec()));
+    }
+  }
+
+  fn process(&mut self, mut samples: Vec<f32>) {
+    let settings = self.filters.read().map(|s| s.clone()).unwrap_or_default();
+    self
+      .chain
+      .configure(&settings, self.sample_rate, self.channels);
+    self.chain.apply(&mut samples);
+    self.loudness.apply(
+      &mut samples,
+      self.sample_rate,
+      self.channels,
+      settings.normalize,
+    );
+    self.pending.extend(samples);
+
+    // Playing the frames back faster or slower shifts tempo and pitch together
+    let speed = f64::from(settings.preset.speed());
+    let channels = self.channels;
+    let frames = self.pending.len() / channels;
+    while (self.cursor as usize) + 1 < frames {
+      let index = self.cursor as usize;
+      let fraction = (self.cursor - index as f64) as f32;
+      for channel in 0..channels {
+        let a = self.pending[index * channels + channel];
+        let b = self.pending[(index + 1) * channels + channel];
+        let sample = (a + (b - a) * fraction).clamp(-1.0, 1.0);
+        self.output.extend_from_slice(&sample.to_le_bytes());
+      }
+      self.cursor += speed;
+    }
+
+    let consumed = (self.cursor as usize).min(frames);
+    self.pending.drain(..consumed * channels);
+    self.cursor -= consumed as f64;
+  }
+}
+
+impl Read for FilterStream {
+  fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+    while self.output_pos >= self.output.len() {
+      self.output.clear();
+      self.output_pos = 0;
+      match self.decode_next()? {
+        Some(samples) => self.process(samples),
+        None => return Ok(0),
+      }
+    }
+
+    let count = buf.len().min(self.output.len() - self.output_pos);
+    buf[..count].copy_from_slice(&self.output[self.output_pos..][..count]);
+    self.output_pos += count;
+    self.position += count as u64;
+    Ok(count)
+  }
+}
+
+impl Seek for FilterStream {
+  fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+    let target = match pos {
+      SeekFrom::Start(p) => p,
+      SeekFrom::Current(offset) => self
+        .position
+        .checked_add_signed(offset)
+        .ok_or(IoErrorKind::InvalidInput)?,
+      SeekFrom::End(_) => return Err(IoErrorKind::Unsupported.into()),
+    };
+    if target == self.position {
+      return Ok(target);
+    }
+    if !self.seekable {
+      return Err(IoErrorKind::Unsupported.into());
+    }
+
+    let frame = target.saturating_sub(HEADER_LEN) / (SAMPLE_LEN * self.channels as u64);
+    let speed = self.filters.read().map(|s| s.preset.speed()).unwrap_or(1.0);
+    let seconds = frame as f64 / f64::from(self.sample_rate) * f64::from(speed);
+    self
+      .format
+      .seek(
+        SeekMode::Coarse,
+        SeekTo::Time {
+          time: Time::new(seconds.trunc() as u64, seconds.fract()),
+          track_id: Some(self.track_id),
+        },
+      )
+      .map_err(IoError::other)?;
+
+    self.decoder.reset();
+    self.chain.reset();
+    self.pending.clear();
+    self.cursor = 0.0;
+    self.output.clear();
+    self.output_pos = 0;
+    if target < HEADER_LEN {
+      self
+        .output
+        .extend_from_slice(&self.header[target as usize..]);
+    }
+    self.position = target;
+    Ok(target)
+  }
+}
+
+impl MediaSource for FilterStream {
+  fn is_seekable(&self) -> bool {
+    self.seekable
+  }
+
+  fn byte_len(&self) -> Option<u64> {
+    None
+  }
+}
+
+struct Loudness {
+  mean_square: Option<f32>,
+  target: f32,
+  gain: f32,
+  measured: MeasuredGain,
+}
+
+impl Loudness {
+  fn new(measured: MeasuredGain) -> Self {
+    Self {
+      mean_square: None,
+      target: 1.0,
+      gain: 1.0,
+      measured,
+    }
+  }
+
+  fn apply(&mut self, samples: &mut [f32], sample_rate: u32, channels: usize, enabled: bool) {
+    let frames = samples.len() / channels;
+    if frames == 0 {
+      return;
+    }
+
+    let block = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    // Start from the first block's level so the opening seconds aren't over-amplified
+    let mean_square = self.mean_square.get_or_insert(block);
+    let window = frames as f32 / (sample_rate as f32 * LOUDNESS_WINDOW_SECS);
+    *mean_square += (block - *mean_square) * window.min(1.0);
+
+    let rms = mean_square.sqrt();
+    let max_gain = 10f32.powf(MAX_NORMALIZE_DB / 20.0);
+    // Quiet passages keep the previous gain instead of being pulled up to the target
+    if rms >= SILENCE_RMS {
+      self.target = (TARGET_RMS / rms).clamp(1.0 / max_gain, max_gain);
+    }
+    self.measured.set(20.0 * self.target.log10());
+
+    if !enabled {
+      self.gain = 1.0;
+      return;
+    }
+
+    let step = 1.0 / (sample_rate as f32 * GAIN_SMOOTHING_SECS);
+    for frame in samples.chunks_exact_mut(channels) {
+      self.gain += (self.target - self.gain) * step;
+      for sample in frame {
+        *sample *= self.gain;
+      }
+    }
+  }
+}
+
+#[derive(Default)]
+struct FilterChain {
+  settings: Option<FilterSettings>,
+  sample_rate: u32,
+  channels: usize,
+  bands: Vec<Biquad>,
+  // One set of band states per channel
+  states: Vec<BiquadState>,
+  rotation: f32,
+}
+
+impl FilterChain {
+  fn configure(&mut self, settings: &FilterSettings, sample_rate: u32, channels: usize) {
+    if self.settings.as_ref() == Some(settings)
+      && self.sample_rate == sample_rate
+      && self.channels == channels
+    {
+      return;
+    }
+
+    let nyquist = sample_rate as f32 / 2.0;
+    self.bands = EQ_BANDS
+      .iter()
+      .zip(settings.gains())
+      .filter(|(frequency, gain)| *gain != 0.0 && (**frequency as f32) < nyquist)
+      .map(|(frequency, gain)| Biquad::peaking(*frequency as f32, gain, sample_rate as f32))
+      .collect();
+    if self.states.len() != self.bands.len() * channels {
+      self.states = vec![BiquadState::default(); self.bands.len() * channels];
+    }
+
+    self.settings = Some(settings.clone());
+    self.sample_rate = sample_rate;
+    self.channels = channels;
+  }
+
+  fn reset(&mut self) {
+    self.states.fill(BiquadState::default());
+  }
+
+  fn apply(&mut self, samples: &mut [f32]) {
+    let preset = match &self.settings {
+      Some(s) => s.preset,
+      None => return,
+    };
+    let channels = self.channels;
+
+    if !self.bands.is_empty() {
+      for frame in samples.chunks_exact_mut(channels) {
+        for (channel, sample) in frame.iter_mut().enumerate() {
+          for (band, filter) in self.bands.iter().enumerate() {
+            *sample = filter.process(&mut self.states[channel * self.bands.len() + band], *sample);
+          }
+        }
+      }
+    }
+
+    if channels != 2 {
+      return;
+    }
+
+    match preset {
+      // Vocals are usually mixed to the centre, so removing what both channels share drops them
+      FilterPreset::Karaoke => {
+        for frame in samples.chunks_exact_mut(2) {
+          let side = frame[0] - frame[1];
+          frame[0] = side;
+          frame[1] = side;
+        }
+      }
+      FilterPreset::EightD => {
+        let step = 2.0 * PI / (ROTATION_PERIOD_SECS * self.sample_rate as f32);
+        for frame in samples.chunks_exact_mut(2) {
+          let pan = self.rotation.sin();
+          frame[0] *= (1.0 - pan).sqrt();
+          frame[1] *= (1.0 + pan).sqrt();
+          self.rotation = (self.rotation + step) % (2.0 * PI);
+        }
+      }
+      _ => (),
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+struct Biquad {
+  b0: f32,
+  b1: f32,
+  b2: f32,
+  a1: f32,
+  a2: f32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct BiquadState {
+  x1: f32,
+  x2: f32,
+  y1: f32,
+  y2: f32,
+}
+
+impl Biquad {
+  // Peaking EQ from the RBJ audio EQ cookbook
+  fn peaking(frequency: f32, gain_db: f32, sample_rate: f32) -> Self {
+    let a = 10f32.powf(gain_db / 40.0);
+    let w0 = 2.0 * PI * frequency / sample_rate;
+    let alpha = w0.sin() / (2.0 * EQ_Q);
+    let cos_w0 = w0.cos();
+    let a0 = 1.0 + alpha / a;
+
+    Self {
+      b0: (1.0 + alpha * a) / a0,
+      b1: (-2.0 * cos_w0) / a0,
+      b2: (1.0 - alpha * a) / a0,
+      a1: (-2.0 * cos_w0) / a0,
+      a2: (1.0 - alpha / a) / a0,
+    }
+  }
+
+  fn process(&self, state: &mut BiquadState, x: f32) -> f32 {
+    let y = self.b0 * x + self.b1 * state.x1 + self.b2 * state.x2
+      - self.a1 * state.y1
+      - self.a2 * state.y2;
+    state.x2 = state.x1;
+    state.x1 = x;
+    state.y2 = state.y1;
+    state.y1 = y;
+    y
+  }
+}
@@ -9,22 +9,35 @@ use tracing::{error, info};
 mod commands;
 mod config;
 mod constants;
+mod filters;
+mod state;
+mod storage;
 
 struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
   async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-    if let Interaction::Command(command) = interaction {
-      commands::handle_commands(&ctx, command).await;
+    match interaction {
+      Interaction::Command(command) => commands::handle_commands(&ctx, command).await,
+      Interaction::Component(component) => commands::handle_components(&ctx, component).await,
+      Interaction::Autocomplete(interaction) => {
+        commands::handle_autocomplete(&ctx, interaction).await
+      }
+      _ => (),
     }
   }
 
+  async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+    commands::handle_voice_state(&ctx, old, new).await;
+  }
+
   async fn ready(&self, ctx: Context, ready: Ready) {
     let activity = ActivityData::playing("with 🍊");
     ctx.set_activity(Some(activity));
 
     commands::register_commands(&ctx, &ready).await;
+    commands::restore_queues(&ctx).await;
 
     info!("{}#{} running", ready.user.name, ready.user.id);
   }
@@ -36,6 +49,14 @@ async fn main() {
   info!("Tracing initialised");
   let config = config::read_config();
   info!("Config read");
+  let database = match storage::Database::open(&config.database_path) {
+    Ok(d) => d,
+    Err(e) => {
+      error!("Error opening database: {:?}", e);
+      std::process::exit(constants::ErrorCodes::DatabaseError as i32);
+    }
+  };
+  info!("Database opened");
   let intents = GatewayIntents::empty()
     | GatewayIntents::GUILDS
     | GatewayIntents::GUILD_MESSAGES
@@ -43,16 +64,54 @@ async fn main() {
 
   info!("Intents: {:?}", intents);
 
+  let songbird = songbird::Songbird::serenity();
+
   let mut client = Client::builder(config.token.clone(), intents)
     .event_handler(Handler)
     .application_id(config.application_id)
-    .register_songbird()
+    .register_songbird_with(songbird.clone())
     .type_map_insert::<constants::HttpKey>(constants::HttpClient::new())
     .type_map_insert::<config::ConfigStorage>(Arc::new(config))
+    .type_map_insert::<state::GuildStateStorage>(Default::default())
+    .type_map_insert::<storage::DatabaseStorage>(Arc::new(database))
+    .type_map_insert::<commands::AutocompleteStorage>(Default::default())
     .await
     .expect("Error creating client");
 
+  tokio::spawn(commands::save_queues_periodically(
+    songbird.clone(),
+    client.data.clone(),
+  ));
+
+  let shard_manager = client.shard_manager.clone();
+  let data = client.data.clone();
+  tokio::spawn(async move {
+    wait_for_shutdown().await;
+    info!("Shutting down, saving queues");
+    commands::save_queues(&songbird, &data).await;
+    shard_manager.shutdown_all().await;
+  });
+
   if let Err(e) = client.start().await {
     error!("Client error: {:?}", e)
   }
 }
+
+async fn wait_for_shutdown() {
+  let interrupt = tokio::signal::ctrl_c();
+
+  #[cfg(unix)]
+  {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+      .expect("Error listening for SIGTERM");
+    tokio::select! {
+      _ = interrupt => (),
+      _ = terminate.recv() => (),
+    }
+  }
+
+  #[cfg(not(unix))]
+  if let Err(e) = interrupt.await {
+    error!("Error listening for Ctrl+C: {}", e);
+  }
+}
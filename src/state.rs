@@ -0,0 +1,136 @@
+use crate::filters::SharedFilters;
+use crate::storage::GuildSettings;
+use serenity::{
+  client::Context,
+  model::id::{ChannelId, GuildId, MessageId, UserId},
+  prelude::{RwLock, TypeMapKey},
+};
+use songbird::tracks::TrackHandle;
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+  time::Duration,
+};
+use tokio::task::JoinHandle;
+
+pub struct GuildStateStorage;
+
+impl TypeMapKey for GuildStateStorage {
+  type Value = Arc<RwLock<HashMap<GuildId, GuildState>>>;
+}
+
+#[derive(Default)]
+pub struct GuildState {
+  pub loop_mode: LoopMode,
+  pub now_playing: Option<NowPlaying>,
+  // Loaded from the database on first use
+  pub settings: Option<GuildSettings>,
+  // Shared with every source enqueued in the guild so changes apply mid-track
+  pub filters: SharedFilters,
+  pub skip_vote: Option<SkipVote>,
+  // Where the last command was used, for notices that aren't replies
+  pub text_channel: Option<ChannelId>,
+  pub idle_watcher: Option<JoinHandle<()>>,
+  pub pending_leave: Option<PendingLeave>,
+  // The channel the bot was asked to be in, rejoined if the connection drops
+  pub voice_channel: Option<ChannelId>,
+  pub recovery: Option<Recovery>,
+}
+
+pub struct NowPlaying {
+  pub channel_id: ChannelId,
+  pub message_id: MessageId,
+  pub updater: JoinHandle<()>,
+}
+
+// Votes to skip one track, identified by its handle's uuid
+pub struct SkipVote {
+  pub track_id: u128,
+  pub voters: HashSet<UserId>,
+}
+
+pub struct PendingLeave {
+  pub reason: LeaveReason,
+  // Whether playback was paused for the grace period and should resume if it's called off
+  pub paused: bool,
+  pub task: JoinHandle<()>,
+}
+
+pub struct Recovery {
+  // The track paused when the connection dropped and where it was
+  pub resume: Option<(TrackHandle, Duration)>,
+  pub task: JoinHandle<()>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LeaveReason {
+  Alone,
+  Idle,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum LoopMode {
+  #[default]
+  Off,
+  Track,
+  Queue,
+}
+
+impl LoopMode {
+  pub const ALL: [LoopMode; 3] = [LoopMode::Track, LoopMode::Queue, LoopMode::Off];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Off => "off",
+      Self::Track => "track",
+      Self::Queue => "queue",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|m| m.name() == name)
+  }
+
+  pub fn next(&self) -> Self {
+    match self {
+      Self::Off => Self::Track,
+      Self::Track => Self::Queue,
+      Self::Queue => Self::Off,
+    }
+  }
+}
+
+impl std::fmt::Display for LoopMode {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+async fn storage(ctx: &Context) -> Arc<RwLock<HashMap<GuildId, GuildState>>> {
+  let data = ctx.data.read().await;
+  data
+    .get::<GuildStateStorage>()
+    .expect("No guild state in global storage")
+    .clone()
+}
+
+pub async fn read_guild_state<F, T>(ctx: &Context, guild_id: GuildId, f: F) -> T
+where
+  F: FnOnce(&GuildState) -> T,
+{
+  let storage = storage(ctx).await;
+  let states = storage.read().await;
+  match states.get(&guild_id) {
+    Some(state) => f(state),
+    None => f(&GuildState::default()),
+  }
+}
+
+pub async fn modify_guild_state<F, T>(ctx: &Context, guild_id: GuildId, f: F) -> T
+where
+  F: FnOnce(&mut GuildState) -> T,
+{
+  let storage = storage(ctx).await;
+  let mut states = storage.write().await;
+  f(states.entry(guild_id).or_default())
+}
@@ -0,0 +1,123 @@
+use super::{Database, Result};
+use rusqlite::{params, OptionalExtension};
+use serenity::model::id::{GuildId, RoleId};
+
+#[derive(Clone, Debug)]
+pub struct GuildSettings {
+  pub volume: u16,
+  pub normalize: bool,
+  pub crossfade_secs: u16,
+  // Share of listeners needed to skip, 0 lets anyone skip straight away
+  pub skip_vote_percent: u16,
+  pub dj_role_id: Option<RoleId>,
+  pub channel_policy: ChannelPolicy,
+}
+
+impl Default for GuildSettings {
+  fn default() -> Self {
+    Self {
+      volume: 100,
+      normalize: false,
+      crossfade_secs: 0,
+      skip_vote_percent: 0,
+      dj_role_id: None,
+      channel_policy: ChannelPolicy::default(),
+    }
+  }
+}
+
+// What happens when someone uses the bot from a different voice channel than the one it's in
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ChannelPolicy {
+  // Stays put while anyone is listening, moves once the channel is empty
+  #[default]
+  Locked,
+  // Moves the whole session, queue included, to whoever used it
+  Follow,
+}
+
+impl ChannelPolicy {
+  pub const ALL: [ChannelPolicy; 2] = [ChannelPolicy::Locked, ChannelPolicy::Follow];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Locked => "locked",
+      Self::Follow => "follow",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|p| p.name() == name)
+  }
+}
+
+impl std::fmt::Display for ChannelPolicy {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl Database {
+  pub async fn guild_settings(&self, guild_id: GuildId) -> Result<GuildSettings> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        let settings = connection
+          .query_row(
+            "SELECT volume, normalize, crossfade_secs, skip_vote_percent, dj_role_id,
+                    channel_policy
+             FROM guild_settings WHERE guild_id = ?1",
+            params![guild_id],
+            |row| {
+              Ok(GuildSettings {
+                volume: row.get(0)?,
+                normalize: row.get(1)?,
+                crossfade_secs: row.get(2)?,
+                skip_vote_percent: row.get(3)?,
+                dj_role_id: row.get::<_, Option<i64>>(4)?.map(|r| RoleId::new(r as u64)),
+                channel_policy: ChannelPolicy::from_name(&row.get::<_, String>(5)?)
+                  .unwrap_or_default(),
+              })
+            },
+          )
+          .optional()?;
+        Ok(settings.unwrap_or_default())
+      })
+      .await
+  }
+
+  pub async fn save_guild_settings(
+    &self,
+    guild_id: GuildId,
+    settings: GuildSettings,
+  ) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        connection.execute(
+          "INSERT INTO guild_settings
+           (guild_id, volume, normalize, crossfade_secs, skip_vote_percent, dj_role_id,
+            channel_policy)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+           ON CONFLICT (guild_id) DO UPDATE
+           SET volume = excluded.volume,
+               normalize = excluded.normalize,
+               crossfade_secs = excluded.crossfade_secs,
+               skip_vote_percent = excluded.skip_vote_percent,
+               dj_role_id = excluded.dj_role_id,
+               channel_policy = excluded.channel_policy",
+          params![
+            guild_id,
+            settings.volume,
+            settings.normalize,
+            settings.crossfade_secs,
+            settings.skip_vote_percent,
+            settings.dj_role_id.map(|r| r.get() as i64),
+            settings.channel_policy.name()
+          ],
+        )?;
+        Ok(())
+      })
+      .await
+  }
+}
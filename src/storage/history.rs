@@ -0,0 +1,84 @@
+use super::{timestamp, Database, Result};
+use rusqlite::params;
+use serenity::model::id::GuildId;
+use std::time::Duration;
+
+const HISTORY_LIMIT: i64 = 500;
+
+pub struct NewHistoryEntry {
+  pub title: String,
+  pub url: String,
+  pub duration: Duration,
+  pub thumbnail: Option<String>,
+  pub requested_by: Option<String>,
+  pub source_kind: Option<String>,
+}
+
+pub struct HistoryEntry {
+  pub title: String,
+  pub url: String,
+  pub duration: Duration,
+  pub thumbnail: Option<String>,
+  pub requested_by: Option<String>,
+  pub played_at: i64,
+  pub source_kind: Option<String>,
+}
+
+impl Database {
+  pub async fn record_play(&self, guild_id: GuildId, entry: NewHistoryEntry) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        connection.execute(
+          "INSERT INTO play_history
+             (guild_id, title, url, duration_ms, played_at, thumbnail, requested_by, source_kind)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+          params![
+            guild_id,
+            entry.title,
+            entry.url,
+            entry.duration.as_millis() as i64,
+            timestamp(),
+            entry.thumbnail,
+            entry.requested_by,
+            entry.source_kind
+          ],
+        )?;
+        connection.execute(
+          "DELETE FROM play_history WHERE guild_id = ?1 AND id NOT IN (
+             SELECT id FROM play_history WHERE guild_id = ?1 ORDER BY id DESC LIMIT ?2
+           )",
+          params![guild_id, HISTORY_LIMIT],
+        )?;
+        Ok(())
+      })
+      .await
+  }
+
+  pub async fn recent_history(&self, guild_id: GuildId, limit: usize) -> Result<Vec<HistoryEntry>> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        let mut statement = connection.prepare(
+          "SELECT title, url, duration_ms, thumbnail, requested_by, played_at, source_kind
+           FROM play_history
+           WHERE guild_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let entries = statement
+          .query_map(params![guild_id, limit as i64], |row| {
+            Ok(HistoryEntry {
+              title: row.get(0)?,
+              url: row.get(1)?,
+              duration: Duration::from_millis(row.get::<_, i64>(2)? as u64),
+              thumbnail: row.get(3)?,
+              requested_by: row.get(4)?,
+              played_at: row.get(5)?,
+              source_kind: row.get(6)?,
+            })
+          })?
+          .collect();
+        entries
+      })
+      .await
+  }
+}
@@ -0,0 +1,108 @@
+use rusqlite::Connection;
+use tracing::info;
+
+// Applied in order, the database's user_version is the number of migrations already run
+const MIGRATIONS: &[&str] = &[
+  r#"
+  CREATE TABLE guild_settings (
+    guild_id INTEGER PRIMARY KEY,
+    volume INTEGER NOT NULL DEFAULT 100
+  );
+
+  CREATE TABLE play_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    played_at INTEGER NOT NULL
+  );
+  CREATE INDEX play_history_guild ON play_history (guild_id, played_at DESC);
+
+  CREATE TABLE playlists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    owner_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    shared INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    UNIQUE (guild_id, owner_id, name)
+  );
+
+  CREATE TABLE playlist_tracks (
+    playlist_id INTEGER NOT NULL REFERENCES playlists (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    PRIMARY KEY (playlist_id, position)
+  );
+"#,
+  r#"
+  ALTER TABLE playlist_tracks ADD COLUMN thumbnail TEXT;
+"#,
+  r#"
+  CREATE TABLE queue_snapshots (
+    guild_id INTEGER PRIMARY KEY,
+    voice_channel_id INTEGER NOT NULL,
+    text_channel_id INTEGER,
+    position_ms INTEGER NOT NULL,
+    saved_at INTEGER NOT NULL
+  );
+
+  CREATE TABLE queue_snapshot_tracks (
+    guild_id INTEGER NOT NULL REFERENCES queue_snapshots (guild_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    thumbnail TEXT,
+    PRIMARY KEY (guild_id, position)
+  );
+"#,
+  r#"
+  ALTER TABLE guild_settings ADD COLUMN normalize INTEGER NOT NULL DEFAULT 0;
+"#,
+  r#"
+  ALTER TABLE guild_settings ADD COLUMN crossfade_secs INTEGER NOT NULL DEFAULT 0;
+"#,
+  r#"
+  ALTER TABLE guild_settings ADD COLUMN skip_vote_percent INTEGER NOT NULL DEFAULT 0;
+  ALTER TABLE guild_settings ADD COLUMN dj_role_id INTEGER;
+"#,
+  r#"
+  CREATE TABLE command_permissions (
+    guild_id INTEGER NOT NULL,
+    command TEXT NOT NULL,
+    target_kind TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, command, target_kind, target_id)
+  );
+"#,
+  r#"
+  ALTER TABLE guild_settings ADD COLUMN channel_policy TEXT NOT NULL DEFAULT 'locked';
+"#,
+  r#"
+  ALTER TABLE play_history ADD COLUMN thumbnail TEXT;
+  ALTER TABLE play_history ADD COLUMN requested_by TEXT;
+"#,
+  r#"
+  ALTER TABLE playlist_tracks ADD COLUMN source_kind TEXT;
+  ALTER TABLE queue_snapshot_tracks ADD COLUMN source_kind TEXT;
+  ALTER TABLE play_history ADD COLUMN source_kind TEXT;
+"#,
+];
+
+pub fn run(connection: &mut Connection) -> rusqlite::Result<()> {
+  let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+  for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(migration)?;
+    transaction.pragma_update(None, "user_version", i + 1)?;
+    transaction.commit()?;
+    info!("Applied database migration {}", i + 1);
+  }
+
+  Ok(())
+}
@@ -0,0 +1,76 @@
+use rusqlite::Connection;
+use serenity::{client::Context, prelude::TypeMapKey};
+use std::{
+  path::Path,
+  sync::{Arc, Mutex},
+};
+
+mod history;
+mod migrations;
+mod snapshots;
+
+mod guild_settings;
+mod permissions;
+mod playlists;
+
+pub use guild_settings::{ChannelPolicy, GuildSettings};
+pub use history::{HistoryEntry, NewHistoryEntry};
+pub use permissions::{CommandPermission, PermissionTarget};
+pub use playlists::{PlaylistTrack, SavedPlaylist};
+pub use snapshots::QueueSnapshot;
+
+pub type Result<T> = rusqlite::Result<T>;
+
+pub struct DatabaseStorage;
+
+impl TypeMapKey for DatabaseStorage {
+  type Value = Arc<Database>;
+}
+
+pub struct Database {
+  connection: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+    if let Some(parent) = path.as_ref().parent() {
+      if let Err(e) = std::fs::create_dir_all(parent) {
+        tracing::warn!("Couldn't create database directory: {}", e);
+      }
+    }
+
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+    migrations::run(&mut connection)?;
+
+    Ok(Self {
+      connection: Arc::new(Mutex::new(connection)),
+    })
+  }
+
+  async fn call<F, T>(&self, f: F) -> Result<T>
+  where
+    F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+  {
+    let connection = self.connection.clone();
+    tokio::task::spawn_blocking(move || {
+      let mut connection = connection.lock().expect("Database connection poisoned");
+      f(&mut connection)
+    })
+    .await
+    .expect("Database task panicked")
+  }
+}
+
+pub async fn database(ctx: &Context) -> Arc<Database> {
+  let data = ctx.data.read().await;
+  data
+    .get::<DatabaseStorage>()
+    .expect("No database in global storage")
+    .clone()
+}
+
+fn timestamp() -> i64 {
+  chrono::Utc::now().timestamp()
+}
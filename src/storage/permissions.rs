@@ -0,0 +1,120 @@
+use super::{Database, Result};
+use rusqlite::{params, Row};
+use serenity::model::id::{GuildId, RoleId, UserId};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PermissionTarget {
+  Role(RoleId),
+  User(UserId),
+}
+
+impl PermissionTarget {
+  fn kind(&self) -> &'static str {
+    match self {
+      Self::Role(_) => "role",
+      Self::User(_) => "user",
+    }
+  }
+
+  fn id(&self) -> i64 {
+    match self {
+      Self::Role(r) => r.get() as i64,
+      Self::User(u) => u.get() as i64,
+    }
+  }
+
+  pub fn mention(&self) -> String {
+    match self {
+      Self::Role(r) => format!("<@&{}>", r),
+      Self::User(u) => format!("<@{}>", u),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct CommandPermission {
+  pub command: String,
+  pub target: PermissionTarget,
+}
+
+fn permission_from_row(row: &Row) -> rusqlite::Result<Option<CommandPermission>> {
+  let command: String = row.get(0)?;
+  let kind: String = row.get(1)?;
+  let id = row.get::<_, i64>(2)? as u64;
+  let target = match kind.as_str() {
+    "role" => PermissionTarget::Role(RoleId::new(id)),
+    "user" => PermissionTarget::User(UserId::new(id)),
+    _ => return Ok(None),
+  };
+  Ok(Some(CommandPermission { command, target }))
+}
+
+impl Database {
+  pub async fn command_permissions(&self, guild_id: GuildId) -> Result<Vec<CommandPermission>> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        let mut statement = connection.prepare(
+          "SELECT command, target_kind, target_id FROM command_permissions
+           WHERE guild_id = ?1 ORDER BY command",
+        )?;
+        let permissions = statement
+          .query_map(params![guild_id], permission_from_row)?
+          .collect::<Result<Vec<_>>>()?;
+        Ok(permissions.into_iter().flatten().collect())
+      })
+      .await
+  }
+
+  pub async fn add_command_permission(
+    &self,
+    guild_id: GuildId,
+    command: String,
+    target: PermissionTarget,
+  ) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        connection.execute(
+          "INSERT OR IGNORE INTO command_permissions (guild_id, command, target_kind, target_id)
+           VALUES (?1, ?2, ?3, ?4)",
+          params![guild_id, command, target.kind(), target.id()],
+        )?;
+        Ok(())
+      })
+      .await
+  }
+
+  // Returns whether a rule was removed
+  pub async fn remove_command_permission(
+    &self,
+    guild_id: GuildId,
+    command: String,
+    target: PermissionTarget,
+  ) -> Result<bool> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        let removed = connection.execute(
+          "DELETE FROM command_permissions
+           WHERE guild_id = ?1 AND command = ?2 AND target_kind = ?3 AND target_id = ?4",
+          params![guild_id, command, target.kind(), target.id()],
+        )?;
+        Ok(removed > 0)
+      })
+      .await
+  }
+
+  pub async fn clear_command_permissions(&self, guild_id: GuildId, command: String) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    self
+      .call(move |connection| {
+        connection.execute(
+          "DELETE FROM command_permissions WHERE guild_id = ?1 AND command = ?2",
+          params![guild_id, command],
+        )?;
+        Ok(())
+      })
+      .await
+  }
+}
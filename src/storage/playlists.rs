@@ -0,0 +1,171 @@
+use super::{timestamp, Database, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use serenity::model::id::{GuildId, UserId};
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct SavedPlaylist {
+  pub id: i64,
+  pub name: String,
+  pub shared: bool,
+  pub track_count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlaylistTrack {
+  pub title: String,
+  pub url: String,
+  pub duration: Duration,
+  pub thumbnail: Option<String>,
+  // How the link is played, missing on tracks saved before it was recorded
+  pub source_kind: Option<String>,
+}
+
+const PLAYLIST_COLUMNS: &str = "p.id, p.name, p.shared,
+  (SELECT COUNT(*) FROM playlist_tracks t WHERE t.playlist_id = p.id)";
+
+fn playlist_from_row(row: &Row) -> rusqlite::Result<SavedPlaylist> {
+  Ok(SavedPlaylist {
+    id: row.get(0)?,
+    name: row.get(1)?,
+    shared: row.get(2)?,
+    track_count: row.get::<_, i64>(3)? as usize,
+  })
+}
+
+impl Database {
+  pub async fn save_playlist(
+    &self,
+    guild_id: GuildId,
+    owner_id: UserId,
+    name: String,
+    tracks: Vec<PlaylistTrack>,
+  ) -> Result<i64> {
+    let guild_id = guild_id.get() as i64;
+    let owner_id = owner_id.get() as i64;
+    self
+      .call(move |connection| {
+        let transaction = connection.transaction()?;
+        transaction.execute(
+          "INSERT INTO playlists (guild_id, owner_id, name, created_at) VALUES (?1, ?2, ?3, ?4)
+           ON CONFLICT (guild_id, owner_id, name) DO UPDATE SET created_at = excluded.created_at",
+          params![guild_id, owner_id, name, timestamp()],
+        )?;
+        let id: i64 = transaction.query_row(
+          "SELECT id FROM playlists WHERE guild_id = ?1 AND owner_id = ?2 AND name = ?3",
+          params![guild_id, owner_id, name],
+          |row| row.get(0),
+        )?;
+
+        transaction.execute(
+          "DELETE FROM playlist_tracks WHERE playlist_id = ?1",
+          params![id],
+        )?;
+        for (position, track) in tracks.iter().enumerate() {
+          transaction.execute(
+            "INSERT INTO playlist_tracks
+             (playlist_id, position, title, url, duration_ms, thumbnail, source_kind)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+              id,
+              position as i64,
+              track.title,
+              track.url,
+              track.duration.as_millis() as i64,
+              track.thumbnail,
+              track.source_kind
+            ],
+          )?;
+        }
+
+        transaction.commit()?;
+        Ok(id)
+      })
+      .await
+  }
+
+  pub async fn playlist(
+    &self,
+    guild_id: GuildId,
+    owner_id: UserId,
+    name: String,
+  ) -> Result<Option<SavedPlaylist>> {
+    let guild_id = guild_id.get() as i64;
+    let owner_id = owner_id.get() as i64;
+    self
+      .call(move |connection| {
+        connection
+          .query_row(
+            &format!(
+              "SELECT {} FROM playlists p WHERE p.guild_id = ?1 AND p.owner_id = ?2 AND p.name = ?3",
+              PLAYLIST_COLUMNS
+            ),
+            params![guild_id, owner_id, name],
+            playlist_from_row,
+          )
+          .optional()
+      })
+      .await
+  }
+
+  pub async fn playlists(&self, guild_id: GuildId, owner_id: UserId) -> Result<Vec<SavedPlaylist>> {
+    let guild_id = guild_id.get() as i64;
+    let owner_id = owner_id.get() as i64;
+    self
+      .call(move |connection| {
+        let mut statement = connection.prepare(&format!(
+          "SELECT {} FROM playlists p WHERE p.guild_id = ?1 AND p.owner_id = ?2 ORDER BY p.name",
+          PLAYLIST_COLUMNS
+        ))?;
+        let playlists = statement
+          .query_map(params![guild_id, owner_id], playlist_from_row)?
+          .collect();
+        playlists
+      })
+      .await
+  }
+
+  pub async fn playlist_tracks(&self, playlist_id: i64) -> Result<Vec<PlaylistTrack>> {
+    self
+      .call(move |connection| {
+        let mut statement = connection.prepare(
+          "SELECT title, url, duration_ms, thumbnail, source_kind FROM playlist_tracks
+           WHERE playlist_id = ?1 ORDER BY position",
+        )?;
+        let tracks = statement
+          .query_map(params![playlist_id], |row| {
+            Ok(PlaylistTrack {
+              title: row.get(0)?,
+              url: row.get(1)?,
+              duration: Duration::from_millis(row.get::<_, i64>(2)? as u64),
+              thumbnail: row.get(3)?,
+              source_kind: row.get(4)?,
+            })
+          })?
+          .collect();
+        tracks
+      })
+      .await
+  }
+
+  pub async fn set_playlist_shared(&self, playlist_id: i64, shared: bool) -> Result<()> {
+    self
+      .call(move |connection| {
+        connection.execute(
+          "UPDATE playlists SET shared = ?2 WHERE id = ?1",
+          params![playlist_id, shared],
+        )?;
+        Ok(())
+      })
+      .await
+  }
+
+  pub async fn delete_playlist(&self, playlist_id: i64) -> Result<()> {
+    self
+      .call(move |connection| {
+        connection.execute("DELETE FROM playlists WHERE id = ?1", params![playlist_id])?;
+        Ok(())
+      })
+      .await
+  }
+}
@@ -0,0 +1,104 @@
+use super::{timestamp, Database, PlaylistTrack, Result};
+use rusqlite::params;
+use serenity::model::id::{ChannelId, GuildId};
+use std::time::Duration;
+
+pub struct QueueSnapshot {
+  pub guild_id: GuildId,
+  pub voice_channel_id: ChannelId,
+  pub text_channel_id: Option<ChannelId>,
+  pub position: Duration,
+  pub tracks: Vec<PlaylistTrack>,
+}
+
+impl Database {
+  pub async fn replace_queue_snapshots(&self, snapshots: Vec<QueueSnapshot>) -> Result<()> {
+    self
+      .call(move |connection| {
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM queue_snapshots", [])?;
+
+        let saved_at = timestamp();
+        for snapshot in &snapshots {
+          let guild_id = snapshot.guild_id.get() as i64;
+          transaction.execute(
+            "INSERT INTO queue_snapshots
+             (guild_id, voice_channel_id, text_channel_id, position_ms, saved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+              guild_id,
+              snapshot.voice_channel_id.get() as i64,
+              snapshot.text_channel_id.map(|c| c.get() as i64),
+              snapshot.position.as_millis() as i64,
+              saved_at
+            ],
+          )?;
+
+          for (position, track) in snapshot.tracks.iter().enumerate() {
+            transaction.execute(
+              "INSERT INTO queue_snapshot_tracks
+               (guild_id, position, title, url, duration_ms, thumbnail, source_kind)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+              params![
+                guild_id,
+                position as i64,
+                track.title,
+                track.url,
+                track.duration.as_millis() as i64,
+                track.thumbnail,
+                track.source_kind
+              ],
+            )?;
+          }
+        }
+
+        transaction.commit()
+      })
+      .await
+  }
+
+  pub async fn queue_snapshots(&self, max_age: Duration) -> Result<Vec<QueueSnapshot>> {
+    let oldest = timestamp() - max_age.as_secs() as i64;
+    self
+      .call(move |connection| {
+        let mut statement = connection.prepare(
+          "SELECT guild_id, voice_channel_id, text_channel_id, position_ms FROM queue_snapshots
+           WHERE saved_at >= ?1",
+        )?;
+        let mut snapshots = statement
+          .query_map(params![oldest], |row| {
+            Ok(QueueSnapshot {
+              guild_id: GuildId::new(row.get::<_, i64>(0)? as u64),
+              voice_channel_id: ChannelId::new(row.get::<_, i64>(1)? as u64),
+              text_channel_id: row
+                .get::<_, Option<i64>>(2)?
+                .map(|c| ChannelId::new(c as u64)),
+              position: Duration::from_millis(row.get::<_, i64>(3)? as u64),
+              tracks: vec![],
+            })
+          })?
+          .collect::<Result<Vec<_>>>()?;
+
+        let mut statement = connection.prepare(
+          "SELECT title, url, duration_ms, thumbnail, source_kind FROM queue_snapshot_tracks
+           WHERE guild_id = ?1 ORDER BY position",
+        )?;
+        for snapshot in &mut snapshots {
+          snapshot.tracks = statement
+            .query_map(params![snapshot.guild_id.get() as i64], |row| {
+              Ok(PlaylistTrack {
+                title: row.get(0)?,
+                url: row.get(1)?,
+                duration: Duration::from_millis(row.get::<_, i64>(2)? as u64),
+                thumbnail: row.get(3)?,
+                source_kind: row.get(4)?,
+              })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        }
+
+        Ok(snapshots)
+      })
+      .await
+  }
+}